@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+
+use crate::parser::{Expr, Stmt};
+use crate::visit::{walk_stmt, walk_stmts, Visitor};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArityMismatch {
+    pub name: String,
+    pub expected: usize,
+    pub found: usize,
+}
+
+// Checks calls to functions declared anywhere in the program; calls to
+// anything else are assumed to be builtins and skipped.
+pub fn check_arity(stmts: &[Stmt]) -> Vec<ArityMismatch> {
+    let mut declarations = DeclarationCollector { arities: HashMap::new() };
+    walk_stmts(&mut declarations, stmts);
+    let mut checker = ArityChecker {
+        arities: declarations.arities,
+        errors: Vec::new(),
+    };
+    walk_stmts(&mut checker, stmts);
+    checker.errors
+}
+
+// The fewest and most arguments each function accepts.
+struct DeclarationCollector {
+    arities: HashMap<String, (usize, usize)>,
+}
+
+impl Visitor for DeclarationCollector {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        if let Stmt::FunctionDeclaration(name, params, _, _) = stmt {
+            let required = params.iter().filter(|(_, default)| default.is_none()).count();
+            self.arities.insert(name.clone(), (required, params.len()));
+        }
+        walk_stmt(self, stmt);
+    }
+}
+
+struct ArityChecker {
+    arities: HashMap<String, (usize, usize)>,
+    errors: Vec<ArityMismatch>,
+}
+
+impl Visitor for ArityChecker {
+    fn visit_call(&mut self, name: &str, args: &[Expr]) {
+        if let Some(&(required, total)) = self.arities.get(name) {
+            if args.len() < required || args.len() > total {
+                let expected = if args.len() < required { required } else { total };
+                self.errors.push(ArityMismatch {
+                    name: name.to_string(),
+                    expected,
+                    found: args.len(),
+                });
+            }
+        }
+        for arg in args {
+            self.visit_expr(arg);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(source: &str) -> Vec<ArityMismatch> {
+        check_arity(&crate::parse_str(source).unwrap())
+    }
+
+    #[test]
+    fn matching_calls_and_builtins_pass() {
+        assert!(check("function f(a, b) { } f(1, 2); honk(1, 2, 3);").is_empty());
+    }
+
+    #[test]
+    fn over_supplied_call_is_reported() {
+        assert_eq!(check("function f(a) { } x = f(1, 2);"), vec![ArityMismatch { name: "f".into(), expected: 1, found: 2 }]);
+    }
+
+    #[test]
+    fn under_supplied_call_is_reported() {
+        assert_eq!(check("g(f()); function f(a, b) { }"), vec![ArityMismatch { name: "f".into(), expected: 2, found: 0 }]);
+    }
+}
@@ -0,0 +1,191 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::interpreter::{Interpreter, RuntimeError, Value};
+use crate::typeck::Type;
+
+// Receives the actuator calls made by a script; hosts implement this to
+// drive real hardware or a simulation.
+pub trait VehicleBackend {
+    fn apply_brakes(&mut self);
+    fn set_speed(&mut self, speed: f64);
+    fn set_steering_angle(&mut self, angle: f64);
+    fn honk(&mut self);
+    fn read_speed(&mut self) -> f64;
+    fn read_battery(&mut self) -> f64;
+}
+
+// Every native the interpreter provides out of the box.
+pub const BUILTINS: &[&str] = &["applyBrakes", "setSpeed", "setSteeringAngle", "honk", "readSpeed", "readBattery", "log", "typeof"];
+
+// What the type checker assumes a call to a builtin returns.
+pub fn return_type(name: &str) -> Option<Type> {
+    match name {
+        "readSpeed" | "readBattery" => Some(Type::Float),
+        "typeof" => Some(Type::Str),
+        _ => None,
+    }
+}
+
+// Builtins that act on the vehicle or the outside world, which a `pure`
+// function may not call.
+pub fn has_side_effects(name: &str) -> bool {
+    matches!(name, "applyBrakes" | "setSpeed" | "setSteeringAngle" | "honk" | "log")
+}
+
+pub fn register_builtins<B: VehicleBackend + 'static>(interp: &mut Interpreter, backend: Rc<RefCell<B>>) {
+    let vehicle = backend.clone();
+    interp.register_native("applyBrakes", move |args| {
+        expect_arity("applyBrakes", args, 0)?;
+        vehicle.borrow_mut().apply_brakes();
+        Ok(Value::Unit)
+    });
+    let vehicle = backend.clone();
+    interp.register_native("setSpeed", move |args| {
+        expect_arity("setSpeed", args, 1)?;
+        vehicle.borrow_mut().set_speed(number_arg("setSpeed", &args[0])?);
+        Ok(Value::Unit)
+    });
+    let vehicle = backend.clone();
+    interp.register_native("setSteeringAngle", move |args| {
+        expect_arity("setSteeringAngle", args, 1)?;
+        vehicle.borrow_mut().set_steering_angle(number_arg("setSteeringAngle", &args[0])?);
+        Ok(Value::Unit)
+    });
+    let vehicle = backend.clone();
+    interp.register_native("honk", move |args| {
+        expect_arity("honk", args, 0)?;
+        vehicle.borrow_mut().honk();
+        Ok(Value::Unit)
+    });
+    let vehicle = backend.clone();
+    interp.register_native("readSpeed", move |args| {
+        expect_arity("readSpeed", args, 0)?;
+        Ok(Value::Float(vehicle.borrow_mut().read_speed()))
+    });
+    let vehicle = backend;
+    interp.register_native("readBattery", move |args| {
+        expect_arity("readBattery", args, 0)?;
+        Ok(Value::Float(vehicle.borrow_mut().read_battery()))
+    });
+}
+
+// `log("message")`, which the interpreter provides by default; the sink
+// receives the message.
+pub(crate) fn log_native(sink: impl Fn(&str) + 'static) -> impl Fn(&[Value]) -> Result<Value, RuntimeError> {
+    move |args| {
+        expect_arity("log", args, 1)?;
+        match &args[0] {
+            Value::Str(message) => {
+                sink(message);
+                Ok(Value::Unit)
+            }
+            other => Err(RuntimeError::TypeMismatch(format!("log expects a string, found {:?}", other))),
+        }
+    }
+}
+
+// `typeof(x)`, naming the runtime type of its argument.
+pub(crate) fn typeof_native(args: &[Value]) -> Result<Value, RuntimeError> {
+    expect_arity("typeof", args, 1)?;
+    let name = match &args[0] {
+        Value::Int(_) => "int",
+        Value::Float(_) => "float",
+        Value::Bool(_) => "bool",
+        Value::Str(_) => "string",
+        Value::Function(_) => "function",
+        Value::Unit => "unit",
+    };
+    Ok(Value::Str(name.to_string()))
+}
+
+fn expect_arity(name: &str, args: &[Value], expected: usize) -> Result<(), RuntimeError> {
+    if args.len() != expected {
+        return Err(RuntimeError::ArityMismatch(name.to_string(), expected, args.len()));
+    }
+    Ok(())
+}
+
+fn number_arg(name: &str, arg: &Value) -> Result<f64, RuntimeError> {
+    match arg {
+        Value::Int(value) => Ok(*value as f64),
+        Value::Float(value) => Ok(*value),
+        other => Err(RuntimeError::TypeMismatch(format!("{} expects a number, found {:?}", name, other))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Records the calls it receives; sensor reads return fixed values.
+    #[derive(Default)]
+    struct MockBackend {
+        calls: Vec<String>,
+    }
+
+    impl VehicleBackend for MockBackend {
+        fn apply_brakes(&mut self) {
+            self.calls.push("applyBrakes".into());
+        }
+
+        fn set_speed(&mut self, speed: f64) {
+            self.calls.push(format!("setSpeed {}", speed));
+        }
+
+        fn set_steering_angle(&mut self, angle: f64) {
+            self.calls.push(format!("setSteeringAngle {}", angle));
+        }
+
+        fn honk(&mut self) {
+            self.calls.push("honk".into());
+        }
+
+        fn read_speed(&mut self) -> f64 {
+            55.0
+        }
+
+        fn read_battery(&mut self) -> f64 {
+            0.8
+        }
+    }
+
+    fn interpreter() -> (Interpreter, Rc<RefCell<MockBackend>>) {
+        let backend = Rc::new(RefCell::new(MockBackend::default()));
+        let mut interp = Interpreter::new();
+        register_builtins(&mut interp, backend.clone());
+        (interp, backend)
+    }
+
+    #[test]
+    fn calls_reach_the_backend() {
+        let (mut interp, backend) = interpreter();
+        interp.run(&crate::parse_str("applyBrakes(); setSpeed(30);").unwrap()).unwrap();
+        assert_eq!(backend.borrow().calls, vec!["applyBrakes", "setSpeed 30"]);
+    }
+
+    #[test]
+    fn unknown_call_names_the_function() {
+        let (mut interp, _) = interpreter();
+        let result = interp.run(&crate::parse_str("launch();").unwrap());
+        assert_eq!(result, Err(RuntimeError::UndefinedFunction("launch".into())));
+    }
+
+    #[test]
+    fn sensor_reads_come_from_the_backend() {
+        let (mut interp, _) = interpreter();
+        interp.run(&crate::parse_str("fast = false; if (readSpeed() > 60) { fast = true; } b = readBattery();").unwrap()).unwrap();
+        assert_eq!(interp.get("fast"), Some(&Value::Bool(false)));
+        assert_eq!(interp.get("b"), Some(&Value::Float(0.8)));
+    }
+
+    #[test]
+    fn typeof_names_the_value_type() {
+        let (mut interp, _) = interpreter();
+        let source = "a = typeof(5); b = typeof(\"hi\"); c = typeof(1.5); d = typeof(1 < 2); function f() {} e = typeof(f);";
+        interp.run(&crate::parse_str(source).unwrap()).unwrap();
+        let types: Vec<_> = ["a", "b", "c", "d", "e"].iter().map(|name| interp.get(name).unwrap().to_string()).collect();
+        assert_eq!(types, ["int", "string", "float", "bool", "function"]);
+        assert!(matches!(interp.run(&crate::parse_str("x = typeof();").unwrap()), Err(RuntimeError::ArityMismatch(..))));
+    }
+}
@@ -0,0 +1,723 @@
+use std::collections::HashMap;
+
+use crate::parser::{Expr, Stmt, StringPart};
+use crate::printer::print_expr;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Op {
+    PushInt(i64),
+    PushFloat(f64),
+    PushBool(bool),
+    PushStr(String),
+    PushUnit,
+    LoadGlobal(usize),
+    StoreGlobal(usize),
+    // Slots of the frame of the running call.
+    LoadLocal(usize),
+    StoreLocal(usize),
+    Pop,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+    Neg,
+    Not,
+    CmpEq,
+    CmpNe,
+    CmpLt,
+    CmpGt,
+    CmpLe,
+    CmpGe,
+    Jump(usize),
+    JumpIfFalse(usize),
+    // Binds a slot to a function, as a declaration does in the interpreter.
+    DefineGlobal(usize, usize),
+    DefineLocal(usize, usize),
+    // Calls whatever a slot is bound to with that many arguments; an unbound
+    // global names a native instead.
+    CallGlobal(usize, usize),
+    CallLocal(usize, usize),
+    // Calls a function of an enclosing body directly.
+    CallFunction(usize, usize),
+    Return,
+    // Pops that many values and pushes them joined as one string.
+    Concat(usize),
+}
+
+impl Op {
+    fn from_binary(op: &str) -> Option<Op> {
+        match op {
+            "+" => Some(Op::Add),
+            "-" => Some(Op::Sub),
+            "*" => Some(Op::Mul),
+            "/" => Some(Op::Div),
+            "%" => Some(Op::Rem),
+            "**" => Some(Op::Pow),
+            "==" => Some(Op::CmpEq),
+            "!=" => Some(Op::CmpNe),
+            "<" => Some(Op::CmpLt),
+            ">" => Some(Op::CmpGt),
+            "<=" => Some(Op::CmpLe),
+            ">=" => Some(Op::CmpGe),
+            _ => None,
+        }
+    }
+
+    // The source operator a binary op was compiled from, so the VM can share
+    // the interpreter's arithmetic.
+    pub(crate) fn binary_symbol(&self) -> Option<&'static str> {
+        match self {
+            Op::Add => Some("+"),
+            Op::Sub => Some("-"),
+            Op::Mul => Some("*"),
+            Op::Div => Some("/"),
+            Op::Rem => Some("%"),
+            Op::Pow => Some("**"),
+            Op::CmpEq => Some("=="),
+            Op::CmpNe => Some("!="),
+            Op::CmpLt => Some("<"),
+            Op::CmpGt => Some(">"),
+            Op::CmpLe => Some("<="),
+            Op::CmpGe => Some(">="),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompileError {
+    Unsupported(String),
+    BreakOutsideLoop,
+    ContinueOutsideLoop,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub name: String,
+    // The local slot each parameter is bound to.
+    pub params: Vec<usize>,
+    // Entry points of the code computing each parameter's default, if any.
+    pub defaults: Vec<Option<usize>>,
+    pub entry: usize,
+    // The names of the slots in each call's frame.
+    pub locals: Vec<String>,
+}
+
+// Top-level code starts at 0 and ends in a `Return`; function and handler
+// bodies follow it. Every call gets a frame of local slots for its
+// parameters and the variables declared in it, as the top-level code does
+// for variables declared in its blocks and loops; everything else is a
+// global. Slots follow the interpreter's scopes: a block or `for` loop's
+// variables are only visible inside it, and assigning a name no enclosing
+// scope declares makes a global. Functions are bound to slots the same way
+// when their declaration runs, so a call only reaches a function declared
+// in scope and already executed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    pub code: Vec<Op>,
+    pub globals: Vec<String>,
+    pub locals: Vec<String>,
+    pub functions: Vec<Function>,
+    pub handlers: HashMap<String, Function>,
+}
+
+pub fn compile(stmts: &[Stmt]) -> Result<Program, CompileError> {
+    let mut compiler = Compiler {
+        code: Vec::new(),
+        globals: HashMap::new(),
+        global_names: Vec::new(),
+        functions: Vec::new(),
+        handlers: HashMap::new(),
+        loops: Vec::new(),
+        pending: Vec::new(),
+        frame: Frame::default(),
+    };
+    compiler.block(stmts)?;
+    compiler.emit(Op::PushUnit);
+    compiler.emit(Op::Return);
+    let locals = std::mem::take(&mut compiler.frame).slots;
+    while let Some(body) = compiler.pending.pop() {
+        match body {
+            Pending::Function(id, name, params, body, captured) => {
+                compiler.frame = Frame {
+                    depth: 1,
+                    captured,
+                    ..Frame::default()
+                };
+                let slots: Vec<usize> = params.iter().map(|(param, _)| compiler.local(param)).collect();
+                let entry = compiler.code.len();
+                compiler.block(body)?;
+                compiler.emit(Op::PushUnit);
+                compiler.emit(Op::Return);
+                // Each default is a separate chunk the VM runs in the new
+                // frame for an omitted argument, seeing only the parameters
+                // before it.
+                let mut defaults = Vec::new();
+                for (i, (_, default)) in params.iter().enumerate() {
+                    defaults.push(match default {
+                        Some(default) => {
+                            compiler.frame.visible = params[..i]
+                                .iter()
+                                .zip(&slots)
+                                .map(|((param, _), &slot)| Local {
+                                    name: param.clone(),
+                                    depth: 1,
+                                    slot,
+                                    function: None,
+                                })
+                                .collect();
+                            let start = compiler.code.len();
+                            compiler.expr(default)?;
+                            compiler.emit(Op::Return);
+                            Some(start)
+                        }
+                        None => None,
+                    });
+                }
+                let function = Function {
+                    name: name.clone(),
+                    params: slots,
+                    defaults,
+                    entry,
+                    locals: std::mem::take(&mut compiler.frame).slots,
+                };
+                compiler.functions[id] = Some(function);
+            }
+            Pending::Handler(event, body, captured) => {
+                compiler.frame = Frame {
+                    depth: 1,
+                    captured,
+                    ..Frame::default()
+                };
+                let entry = compiler.code.len();
+                compiler.block(body)?;
+                compiler.emit(Op::PushUnit);
+                compiler.emit(Op::Return);
+                let handler = Function {
+                    name: event.clone(),
+                    params: Vec::new(),
+                    defaults: Vec::new(),
+                    entry,
+                    locals: std::mem::take(&mut compiler.frame).slots,
+                };
+                compiler.handlers.insert(event.clone(), handler);
+            }
+        }
+    }
+    Ok(Program {
+        code: compiler.code,
+        globals: compiler.global_names,
+        locals,
+        functions: compiler.functions.into_iter().map(|function| function.expect("declared functions are compiled")).collect(),
+        handlers: compiler.handlers,
+    })
+}
+
+// A body waiting to be compiled, with the locals visible where it was
+// declared. The VM has no closures, so the body may only call the
+// functions among them.
+enum Pending<'a> {
+    Function(usize, &'a String, &'a [(String, Option<Expr>)], &'a [Stmt], Vec<Local>),
+    Handler(&'a String, &'a [Stmt], Vec<Local>),
+}
+
+struct Loop {
+    start: usize,
+    breaks: Vec<usize>,
+}
+
+#[derive(Clone)]
+struct Local {
+    name: String,
+    depth: usize,
+    slot: usize,
+    // The function the slot is bound to, while nothing else is stored in
+    // it.
+    function: Option<usize>,
+}
+
+// The frame of the body being compiled. Depth 0 is the top level, whose
+// declarations are globals.
+#[derive(Default)]
+struct Frame {
+    // The names of every slot allocated; a slot is never reused once its
+    // scope ends.
+    slots: Vec<String>,
+    // The locals in scope, innermost last.
+    visible: Vec<Local>,
+    depth: usize,
+    captured: Vec<Local>,
+}
+
+#[derive(Clone, Copy)]
+enum Variable {
+    Global(usize),
+    Local(usize),
+}
+
+struct Compiler<'a> {
+    code: Vec<Op>,
+    globals: HashMap<String, usize>,
+    global_names: Vec<String>,
+    // Filled in as the pending bodies are compiled.
+    functions: Vec<Option<Function>>,
+    handlers: HashMap<String, Function>,
+    loops: Vec<Loop>,
+    // Bodies are compiled after the code that declares them, so a
+    // declaration itself only binds the name.
+    pending: Vec<Pending<'a>>,
+    frame: Frame,
+}
+
+impl<'a> Compiler<'a> {
+    fn emit(&mut self, op: Op) -> usize {
+        self.code.push(op);
+        self.code.len() - 1
+    }
+
+    fn patch(&mut self, at: usize) {
+        let target = self.code.len();
+        match &mut self.code[at] {
+            Op::Jump(to) | Op::JumpIfFalse(to) => *to = target,
+            _ => unreachable!("patching a non-jump op"),
+        }
+    }
+
+    fn global(&mut self, name: &str) -> usize {
+        if let Some(&slot) = self.globals.get(name) {
+            return slot;
+        }
+        self.global_names.push(name.to_string());
+        self.globals.insert(name.to_string(), self.global_names.len() - 1);
+        self.global_names.len() - 1
+    }
+
+    // Binds `name` in the innermost scope, reusing the slot of an earlier
+    // declaration in that same scope.
+    fn local(&mut self, name: &str) -> usize {
+        let frame = &mut self.frame;
+        let mut innermost = frame.visible.iter().rev().take_while(|local| local.depth == frame.depth);
+        if let Some(local) = innermost.find(|local| local.name == name) {
+            return local.slot;
+        }
+        frame.slots.push(name.to_string());
+        let slot = frame.slots.len() - 1;
+        frame.visible.push(Local {
+            name: name.to_string(),
+            depth: frame.depth,
+            slot,
+            function: None,
+        });
+        slot
+    }
+
+    // A `let`, `const` or loop variable.
+    fn declare(&mut self, name: &str) -> Variable {
+        if self.frame.depth == 0 {
+            Variable::Global(self.global(name))
+        } else {
+            Variable::Local(self.local(name))
+        }
+    }
+
+    fn resolve(&mut self, name: &str) -> Result<Variable, CompileError> {
+        if let Some(local) = self.frame.visible.iter().rev().find(|local| local.name == name) {
+            return Ok(Variable::Local(local.slot));
+        }
+        if self.frame.captured.iter().any(|captured| captured.name == name) {
+            return Err(CompileError::Unsupported(format!("closure over local variable {}", name)));
+        }
+        Ok(Variable::Global(self.global(name)))
+    }
+
+    // Calls resolve like variables, except that a function declared in an
+    // enclosing body can be called as long as its slot still holds it.
+    fn call(&mut self, name: &str, argc: usize) -> Result<(), CompileError> {
+        if self.frame.visible.iter().all(|local| local.name != name) {
+            if let Some(captured) = self.frame.captured.iter().rev().find(|captured| captured.name == name) {
+                return match captured.function {
+                    Some(function) => {
+                        self.emit(Op::CallFunction(function, argc));
+                        Ok(())
+                    }
+                    None => Err(CompileError::Unsupported(format!("closure over local variable {}", name))),
+                };
+            }
+        }
+        match self.resolve(name)? {
+            Variable::Global(slot) => self.emit(Op::CallGlobal(slot, argc)),
+            Variable::Local(slot) => self.emit(Op::CallLocal(slot, argc)),
+        };
+        Ok(())
+    }
+
+    fn load(&mut self, variable: Variable) {
+        match variable {
+            Variable::Global(slot) => self.emit(Op::LoadGlobal(slot)),
+            Variable::Local(slot) => self.emit(Op::LoadLocal(slot)),
+        };
+    }
+
+    fn store(&mut self, variable: Variable) {
+        match variable {
+            Variable::Global(slot) => self.emit(Op::StoreGlobal(slot)),
+            Variable::Local(slot) => {
+                self.bind_local(slot, None);
+                self.emit(Op::StoreLocal(slot))
+            }
+        };
+    }
+
+    fn bind_local(&mut self, slot: usize, function: Option<usize>) {
+        for local in self.frame.visible.iter_mut().filter(|local| local.slot == slot) {
+            local.function = function;
+        }
+    }
+
+    // Compiles `body` in a scope of its own, like the interpreter's
+    // environment for a block or loop.
+    fn scoped(&mut self, body: impl FnOnce(&mut Self) -> Result<(), CompileError>) -> Result<(), CompileError> {
+        self.frame.depth += 1;
+        let result = body(self);
+        self.frame.depth -= 1;
+        let depth = self.frame.depth;
+        self.frame.visible.retain(|local| local.depth <= depth);
+        result
+    }
+
+    // The locals a body declared here could see in the interpreter,
+    // innermost last.
+    fn visible(&self) -> Vec<Local> {
+        self.frame.captured.iter().chain(&self.frame.visible).cloned().collect()
+    }
+
+    fn block(&mut self, stmts: &'a [Stmt]) -> Result<(), CompileError> {
+        for stmt in stmts {
+            self.stmt(stmt)?;
+        }
+        Ok(())
+    }
+
+    fn stmt(&mut self, stmt: &'a Stmt) -> Result<(), CompileError> {
+        match stmt {
+            Stmt::VarDecl(name, value) => {
+                match value {
+                    Some(value) => self.expr(value)?,
+                    None => {
+                        self.emit(Op::PushUnit);
+                    }
+                }
+                let variable = self.declare(name);
+                self.store(variable);
+            }
+            Stmt::ConstDecl(name, value) => {
+                self.expr(value)?;
+                let variable = self.declare(name);
+                self.store(variable);
+            }
+            Stmt::Assignment(name, value) => {
+                self.expr(value)?;
+                let variable = self.resolve(name)?;
+                self.store(variable);
+            }
+            Stmt::Increment(name, op) => {
+                let variable = self.resolve(name)?;
+                self.load(variable);
+                self.emit(Op::PushInt(1));
+                self.binary(&op[..1])?;
+                self.store(variable);
+            }
+            Stmt::CompoundAssignment(name, op, value) => {
+                let variable = self.resolve(name)?;
+                self.load(variable);
+                self.expr(value)?;
+                self.binary(op.trim_end_matches('='))?;
+                self.store(variable);
+            }
+            // Bound before the body is queued so the body sees itself and
+            // can recurse.
+            Stmt::FunctionDeclaration(name, params, body, _) => {
+                let id = self.functions.len();
+                self.functions.push(None);
+                match self.declare(name) {
+                    Variable::Global(slot) => {
+                        self.emit(Op::DefineGlobal(slot, id));
+                    }
+                    Variable::Local(slot) => {
+                        self.bind_local(slot, Some(id));
+                        self.emit(Op::DefineLocal(slot, id));
+                    }
+                }
+                let captured = self.visible();
+                self.pending.push(Pending::Function(id, name, params, body, captured));
+            }
+            Stmt::EventHandler(event, body) => {
+                let captured = self.visible();
+                self.pending.push(Pending::Handler(event, body, captured));
+            }
+            Stmt::If(condition, body, else_body) => {
+                self.expr(condition)?;
+                let to_else = self.emit(Op::JumpIfFalse(0));
+                self.block(body)?;
+                match else_body {
+                    Some(else_body) => {
+                        let to_end = self.emit(Op::Jump(0));
+                        self.patch(to_else);
+                        self.block(else_body)?;
+                        self.patch(to_end);
+                    }
+                    None => self.patch(to_else),
+                }
+            }
+            Stmt::IfChain(arms, else_body) => {
+                let mut to_end = Vec::new();
+                for (condition, body) in arms {
+                    self.expr(condition)?;
+                    let to_next = self.emit(Op::JumpIfFalse(0));
+                    self.block(body)?;
+                    to_end.push(self.emit(Op::Jump(0)));
+                    self.patch(to_next);
+                }
+                if let Some(else_body) = else_body {
+                    self.block(else_body)?;
+                }
+                for at in to_end {
+                    self.patch(at);
+                }
+            }
+            Stmt::Switch(subject, cases, default) => {
+                // The subject is kept in a slot no identifier can name, so a
+                // `break` or `return` in a case leaves the stack balanced.
+                self.expr(subject)?;
+                let subject = self.local(&format!("switch#{}", self.code.len()));
+                self.emit(Op::StoreLocal(subject));
+                let mut to_end = Vec::new();
+                for (value, body) in cases {
+                    self.emit(Op::LoadLocal(subject));
+                    self.expr(value)?;
+                    self.emit(Op::CmpEq);
+                    let to_next = self.emit(Op::JumpIfFalse(0));
+                    self.block(body)?;
+                    to_end.push(self.emit(Op::Jump(0)));
+                    self.patch(to_next);
+                }
+                if let Some(default) = default {
+                    self.block(default)?;
+                }
+                for at in to_end {
+                    self.patch(at);
+                }
+            }
+            Stmt::While(condition, body) => {
+                let start = self.code.len();
+                self.expr(condition)?;
+                let to_end = self.emit(Op::JumpIfFalse(0));
+                self.loops.push(Loop { start, breaks: Vec::new() });
+                self.block(body)?;
+                self.emit(Op::Jump(start));
+                self.patch(to_end);
+                let exited = self.loops.pop().expect("loop pushed above");
+                for at in exited.breaks {
+                    self.patch(at);
+                }
+            }
+            // The counter lives in a hidden slot and is copied into the loop
+            // variable each pass, so assigning to the variable in the body
+            // does not change the iteration. `continue` jumps back to the
+            // increment, laid out ahead of the bounds check.
+            Stmt::ForRange(name, range, body) => {
+                let Expr::Range(start, end) = range else {
+                    return Err(CompileError::Unsupported(format!("iterating over {}", print_expr(range))));
+                };
+                let counter = self.local(&format!("for#{}", self.code.len()));
+                let limit = self.local(&format!("for#{}#end", self.code.len()));
+                self.expr(start)?;
+                self.emit(Op::StoreLocal(counter));
+                self.expr(end)?;
+                self.emit(Op::StoreLocal(limit));
+                let to_check = self.emit(Op::Jump(0));
+                let increment = self.code.len();
+                self.emit(Op::LoadLocal(counter));
+                self.emit(Op::PushInt(1));
+                self.emit(Op::Add);
+                self.emit(Op::StoreLocal(counter));
+                self.patch(to_check);
+                self.emit(Op::LoadLocal(counter));
+                self.emit(Op::LoadLocal(limit));
+                self.emit(Op::CmpLt);
+                let to_end = self.emit(Op::JumpIfFalse(0));
+                self.scoped(|compiler| {
+                    let variable = compiler.local(name);
+                    compiler.emit(Op::LoadLocal(counter));
+                    compiler.emit(Op::StoreLocal(variable));
+                    compiler.loops.push(Loop { start: increment, breaks: Vec::new() });
+                    compiler.block(body)
+                })?;
+                self.emit(Op::Jump(increment));
+                self.patch(to_end);
+                let exited = self.loops.pop().expect("loop pushed above");
+                for at in exited.breaks {
+                    self.patch(at);
+                }
+            }
+            // Laid out like a range loop, with the step ahead of the check.
+            Stmt::ForClassic(init, condition, step, body) => self.scoped(|compiler| {
+                compiler.stmt(init)?;
+                let to_check = compiler.emit(Op::Jump(0));
+                let increment = compiler.code.len();
+                compiler.stmt(step)?;
+                compiler.patch(to_check);
+                compiler.expr(condition)?;
+                let to_end = compiler.emit(Op::JumpIfFalse(0));
+                compiler.loops.push(Loop { start: increment, breaks: Vec::new() });
+                compiler.block(body)?;
+                compiler.emit(Op::Jump(increment));
+                compiler.patch(to_end);
+                let exited = compiler.loops.pop().expect("loop pushed above");
+                for at in exited.breaks {
+                    compiler.patch(at);
+                }
+                Ok(())
+            })?,
+            Stmt::Block(body) => self.scoped(|compiler| compiler.block(body))?,
+            Stmt::Break => {
+                let at = self.emit(Op::Jump(0));
+                match self.loops.last_mut() {
+                    Some(enclosing) => enclosing.breaks.push(at),
+                    None => return Err(CompileError::BreakOutsideLoop),
+                }
+            }
+            Stmt::Import(path) => return Err(CompileError::Unsupported(format!("unresolved import {}", path))),
+            Stmt::Continue => match self.loops.last() {
+                Some(enclosing) => {
+                    let start = enclosing.start;
+                    self.emit(Op::Jump(start));
+                }
+                None => return Err(CompileError::ContinueOutsideLoop),
+            },
+            Stmt::Return(value) => {
+                match value {
+                    Some(value) => self.expr(value)?,
+                    None => {
+                        self.emit(Op::PushUnit);
+                    }
+                }
+                self.emit(Op::Return);
+            }
+            Stmt::Expression(expr) => {
+                self.expr(expr)?;
+                self.emit(Op::Pop);
+            }
+        }
+        Ok(())
+    }
+
+    fn expr(&mut self, expr: &'a Expr) -> Result<(), CompileError> {
+        match expr {
+            Expr::NumberLiteral(value, _) => {
+                self.emit(Op::PushInt(*value));
+            }
+            Expr::BoolLiteral(value) => {
+                self.emit(Op::PushBool(*value));
+            }
+            Expr::FloatLiteral(value) => {
+                self.emit(Op::PushFloat(*value));
+            }
+            Expr::StringLiteral(value) => {
+                self.emit(Op::PushStr(value.clone()));
+            }
+            Expr::Quantity(..) => return Err(CompileError::Unsupported("quantity literals".to_string())),
+            Expr::ArrayLiteral(_) | Expr::Index(..) => return Err(CompileError::Unsupported("arrays".to_string())),
+            Expr::Range(..) => return Err(CompileError::Unsupported("ranges outside a for loop".to_string())),
+            Expr::Variable(name, _) => {
+                let variable = self.resolve(name)?;
+                self.load(variable);
+            }
+            Expr::Unary(op, operand) => {
+                self.expr(operand)?;
+                match op.as_str() {
+                    "-" => self.emit(Op::Neg),
+                    "!" => self.emit(Op::Not),
+                    _ => return Err(CompileError::Unsupported(format!("unary operator {}", op))),
+                };
+            }
+            // Jumps past the right operand when the left decides the result;
+            // both operands still go through `JumpIfFalse`, so a non-bool
+            // is rejected as in the interpreter.
+            Expr::Binary(left, op, right) if op == "&&" || op == "||" => {
+                let mut to_false = Vec::new();
+                let mut to_end = Vec::new();
+                self.expr(left)?;
+                let left_false = self.emit(Op::JumpIfFalse(0));
+                if op == "&&" {
+                    to_false.push(left_false);
+                } else {
+                    self.emit(Op::PushBool(true));
+                    to_end.push(self.emit(Op::Jump(0)));
+                    self.patch(left_false);
+                }
+                self.expr(right)?;
+                to_false.push(self.emit(Op::JumpIfFalse(0)));
+                self.emit(Op::PushBool(true));
+                to_end.push(self.emit(Op::Jump(0)));
+                for at in to_false {
+                    self.patch(at);
+                }
+                self.emit(Op::PushBool(false));
+                for at in to_end {
+                    self.patch(at);
+                }
+            }
+            Expr::Binary(left, op, right) => {
+                self.expr(left)?;
+                self.expr(right)?;
+                self.binary(op)?;
+            }
+            Expr::Ternary(condition, then_branch, else_branch) => {
+                self.expr(condition)?;
+                let to_else = self.emit(Op::JumpIfFalse(0));
+                self.expr(then_branch)?;
+                let to_end = self.emit(Op::Jump(0));
+                self.patch(to_else);
+                self.expr(else_branch)?;
+                self.patch(to_end);
+            }
+            Expr::Assign(name, value, _) => {
+                self.expr(value)?;
+                let variable = self.resolve(name)?;
+                self.store(variable);
+                self.load(variable);
+            }
+            Expr::Call(name, args) => {
+                for arg in args {
+                    self.expr(arg)?;
+                }
+                self.call(name, args.len())?;
+            }
+            Expr::Interpolation(parts) => {
+                for part in parts {
+                    match part {
+                        StringPart::Literal(text) => {
+                            self.emit(Op::PushStr(text.clone()));
+                        }
+                        StringPart::Expr(expr) => self.expr(expr)?,
+                    }
+                }
+                self.emit(Op::Concat(parts.len()));
+            }
+        }
+        Ok(())
+    }
+
+    fn binary(&mut self, op: &str) -> Result<(), CompileError> {
+        match Op::from_binary(op) {
+            Some(op) => {
+                self.emit(op);
+                Ok(())
+            }
+            None => Err(CompileError::Unsupported(format!("operator {}", op))),
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use crate::parser::Expr;
+use crate::printer::print_expr;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstEvalError {
+    // A variable, call or assignment, whose value is only known at runtime.
+    NotConstant(String),
+    DivisionByZero,
+    Overflow,
+    Unsupported(String),
+}
+
+// Evaluates integer expressions built only from literals and arithmetic.
+pub fn eval_const(expr: &Expr) -> Result<i64, ConstEvalError> {
+    match expr {
+        Expr::NumberLiteral(value, _) => Ok(*value),
+        Expr::Variable(..) | Expr::Call(..) | Expr::Assign(..) => Err(ConstEvalError::NotConstant(print_expr(expr))),
+        Expr::Unary(op, operand) if op == "-" => eval_const(operand)?.checked_neg().ok_or(ConstEvalError::Overflow),
+        Expr::Binary(left, op, right) => {
+            let left = eval_const(left)?;
+            let right = eval_const(right)?;
+            match op.as_str() {
+                "/" | "%" if right == 0 => Err(ConstEvalError::DivisionByZero),
+                "+" => left.checked_add(right).ok_or(ConstEvalError::Overflow),
+                "-" => left.checked_sub(right).ok_or(ConstEvalError::Overflow),
+                "*" => left.checked_mul(right).ok_or(ConstEvalError::Overflow),
+                "/" => left.checked_div(right).ok_or(ConstEvalError::Overflow),
+                "%" => left.checked_rem(right).ok_or(ConstEvalError::Overflow),
+                "**" => match u32::try_from(right) {
+                    Ok(exponent) => left.checked_pow(exponent).ok_or(ConstEvalError::Overflow),
+                    Err(_) => Err(ConstEvalError::Unsupported(format!("negative exponent {}", right))),
+                },
+                _ => Err(ConstEvalError::Unsupported(format!("operator {}", op))),
+            }
+        }
+        _ => Err(ConstEvalError::Unsupported(print_expr(expr))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Stmt;
+
+    fn eval(source: &str) -> Result<i64, ConstEvalError> {
+        match &crate::parse_str(&format!("x = {};", source)).unwrap()[0] {
+            Stmt::Assignment(_, expr) => eval_const(expr),
+            stmt => panic!("not an assignment: {:?}", stmt),
+        }
+    }
+
+    #[test]
+    fn literal_arithmetic_evaluates() {
+        assert_eq!(eval("2 * (3 + 4)"), Ok(14));
+        assert_eq!(eval("-(3)"), Ok(-3));
+        assert_eq!(eval("1 / (2 - 2)"), Err(ConstEvalError::DivisionByZero));
+    }
+
+    #[test]
+    fn variables_and_calls_are_not_constant() {
+        assert_eq!(eval("x + 1"), Err(ConstEvalError::NotConstant("x".into())));
+        assert_eq!(eval("f(1)"), Err(ConstEvalError::NotConstant("f(1)".into())));
+    }
+}
@@ -0,0 +1,97 @@
+use crate::lexer::LexError;
+use crate::parser::ParseError;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    // Zero for findings that carry no source position.
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Diagnostic {
+    // Renders the message followed by the offending source line with a caret
+    // under the reported column, e.g.
+    //
+    //     error: expected ')', found delimiter "{"
+    //      --> 1:7
+    //       |
+    //     1 | if (a { f(); }
+    //       |       ^
+    pub fn render(&self, source: &str) -> String {
+        let text = source.lines().nth(self.line.saturating_sub(1)).unwrap_or("");
+        let gutter = " ".repeat(self.line.to_string().len());
+        // Tabs are copied into the padding so the caret lines up however
+        // wide the terminal renders them.
+        let padding: String = text
+            .chars()
+            .take(self.column.saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let label = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        format!(
+            "{}: {}\n{}--> {}:{}\n{} |\n{} | {}\n{} | {}^\n",
+            label, self.message, gutter, self.line, self.column, gutter, self.line, text, gutter, padding
+        )
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(err: &ParseError) -> Self {
+        if let ParseError::Lex(err) = err {
+            return Diagnostic::from(err);
+        }
+        let position = err.position();
+        Diagnostic {
+            severity: Severity::Error,
+            message: err.to_string(),
+            line: position.line,
+            column: position.column,
+        }
+    }
+}
+
+impl From<&LexError> for Diagnostic {
+    fn from(err: &LexError) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            message: err.message.clone(),
+            line: err.line,
+            column: err.column,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    #[test]
+    fn renders_the_line_with_a_caret_under_the_column() {
+        let source = "x = 1;\nif (a { f(); }";
+        let err = Parser::try_new(Lexer::new(source)).unwrap().parse().unwrap_err();
+        assert_eq!(
+            Diagnostic::from(&err).render(source),
+            "error: expected ')', found delimiter \"{\"\n --> 2:7\n  |\n2 | if (a { f(); }\n  |       ^\n"
+        );
+    }
+
+    #[test]
+    fn caret_keeps_tabs_aligned() {
+        let source = "\tx = @;";
+        let err = Lexer::new(source).tokenize().unwrap_err();
+        assert!(Diagnostic::from(&err).render(source).ends_with("1 | \tx = @;\n  | \t    ^\n"));
+    }
+}
@@ -0,0 +1,39 @@
+use thiserror::Error;
+
+use crate::interpreter::RuntimeError;
+use crate::lexer::LexError;
+use crate::parser::ParseError;
+use crate::typeck::TypeError;
+
+// One error for every phase, so callers running the whole pipeline can use
+// `?` throughout and still match on where it failed.
+#[derive(Debug, Error)]
+pub enum VacError {
+    #[error("lex error: {0}")]
+    Lex(#[from] LexError),
+    #[error("parse error: {0}")]
+    Parse(#[from] ParseError),
+    #[error("type error: {0}")]
+    Type(#[from] TypeError),
+    #[error("runtime error: {0}")]
+    Runtime(#[from] RuntimeError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_phase_surfaces_its_own_variant() {
+        assert!(matches!(crate::parse_str("x = \"ok\\q\";"), Err(VacError::Lex(_))));
+        assert!(matches!(crate::parse_str("x = ;"), Err(VacError::Parse(ParseError::UnexpectedToken(..)))));
+        assert!(matches!(crate::tokenize_str("a @"), Err(VacError::Lex(_))));
+    }
+
+    #[test]
+    fn errors_display_their_phase() {
+        let err = crate::parse_str("f(").unwrap_err();
+        assert_eq!(err.to_string(), "parse error: expected expression, found end of input");
+        let _: &dyn std::error::Error = &err;
+    }
+}
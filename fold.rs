@@ -0,0 +1,208 @@
+use crate::const_eval::eval_const;
+use crate::parser::{Expr, NumType, Stmt, StringPart};
+
+// Rewrites constant integer arithmetic into literals in place. Anything
+// that would fail at runtime (overflow, division by zero) is left for the
+// interpreter to report.
+pub fn fold_stmts(stmts: &mut [Stmt]) {
+    fold_block(stmts, false);
+}
+
+// `fold_stmts` plus algebraic identities: `x * 1`, `1 * x`, `x + 0`, `0 + x`
+// and `x - 0` become `x`. `x` is still evaluated once and in order, so a call
+// keeps its side effects; `x * 0` is left as is for the same reason.
+pub fn optimize(mut stmts: Vec<Stmt>) -> Vec<Stmt> {
+    fold_block(&mut stmts, true);
+    stmts
+}
+
+fn fold_block(stmts: &mut [Stmt], simplify: bool) {
+    for stmt in stmts {
+        fold_stmt(stmt, simplify);
+    }
+}
+
+fn fold_stmt(stmt: &mut Stmt, simplify: bool) {
+    match stmt {
+        Stmt::VarDecl(_, value) | Stmt::Return(value) => {
+            if let Some(value) = value {
+                fold_expr_with(value, simplify);
+            }
+        }
+        Stmt::ConstDecl(_, value) | Stmt::Assignment(_, value) | Stmt::CompoundAssignment(_, _, value) | Stmt::Expression(value) => {
+            fold_expr_with(value, simplify);
+        }
+        Stmt::FunctionDeclaration(_, params, body, _) => {
+            for default in params.iter_mut().filter_map(|(_, default)| default.as_mut()) {
+                fold_expr_with(default, simplify);
+            }
+            fold_block(body, simplify);
+        }
+        Stmt::EventHandler(_, body) => fold_block(body, simplify),
+        Stmt::If(condition, body, else_body) => {
+            fold_expr_with(condition, simplify);
+            fold_block(body, simplify);
+            if let Some(else_body) = else_body {
+                fold_block(else_body, simplify);
+            }
+        }
+        Stmt::IfChain(arms, else_body) => {
+            for (condition, body) in arms {
+                fold_expr_with(condition, simplify);
+                fold_block(body, simplify);
+            }
+            if let Some(else_body) = else_body {
+                fold_block(else_body, simplify);
+            }
+        }
+        Stmt::Switch(subject, cases, default) => {
+            fold_expr_with(subject, simplify);
+            for (value, body) in cases {
+                fold_expr_with(value, simplify);
+                fold_block(body, simplify);
+            }
+            if let Some(default) = default {
+                fold_block(default, simplify);
+            }
+        }
+        Stmt::While(condition, body) => {
+            fold_expr_with(condition, simplify);
+            fold_block(body, simplify);
+        }
+        Stmt::ForRange(_, range, body) => {
+            fold_expr_with(range, simplify);
+            fold_block(body, simplify);
+        }
+        Stmt::ForClassic(init, condition, step, body) => {
+            fold_stmt(init, simplify);
+            fold_expr_with(condition, simplify);
+            fold_stmt(step, simplify);
+            fold_block(body, simplify);
+        }
+        Stmt::Block(body) => fold_block(body, simplify),
+        Stmt::Break | Stmt::Continue | Stmt::Increment(..) | Stmt::Import(_) => {}
+    }
+}
+
+pub fn fold_expr(expr: &mut Expr) {
+    fold_expr_with(expr, false);
+}
+
+fn fold_expr_with(expr: &mut Expr, simplify: bool) {
+    match expr {
+        Expr::Unary(op, operand) => {
+            fold_expr_with(operand, simplify);
+            match (op.as_str(), &**operand) {
+                ("-", Expr::NumberLiteral(_, NumType::I32)) => fold_const(expr),
+                ("-", Expr::FloatLiteral(value)) => *expr = Expr::FloatLiteral(-value),
+                _ => {}
+            }
+        }
+        Expr::Binary(left, _, right) => {
+            fold_expr_with(left, simplify);
+            fold_expr_with(right, simplify);
+            if matches!(
+                (&**left, &**right),
+                (Expr::NumberLiteral(_, NumType::I32), Expr::NumberLiteral(_, NumType::I32))
+            ) {
+                fold_const(expr);
+            } else if simplify {
+                simplify_identity(expr);
+            }
+        }
+        Expr::Ternary(condition, then_branch, else_branch) => {
+            fold_expr_with(condition, simplify);
+            fold_expr_with(then_branch, simplify);
+            fold_expr_with(else_branch, simplify);
+        }
+        Expr::Assign(_, value, _) => fold_expr_with(value, simplify),
+        Expr::Call(_, args) | Expr::ArrayLiteral(args) => {
+            for arg in args {
+                fold_expr_with(arg, simplify);
+            }
+        }
+        Expr::Index(target, index) | Expr::Range(target, index) => {
+            fold_expr_with(target, simplify);
+            fold_expr_with(index, simplify);
+        }
+        Expr::Interpolation(parts) => {
+            for part in parts {
+                if let StringPart::Expr(expr) = part {
+                    fold_expr_with(expr, simplify);
+                }
+            }
+        }
+        Expr::NumberLiteral(..)
+        | Expr::FloatLiteral(_)
+        | Expr::BoolLiteral(_)
+        | Expr::StringLiteral(_)
+        | Expr::Quantity(..)
+        | Expr::Variable(..) => {}
+    }
+}
+
+fn fold_const(expr: &mut Expr) {
+    // Only `i32` literals are folded, and only into one, so a folded
+    // literal never needs range-checking.
+    if let Some(value) = eval_const(expr).ok().filter(|&value| NumType::I32.contains(value)) {
+        *expr = Expr::NumberLiteral(value, NumType::I32);
+    }
+}
+
+// Only the identities on `i32` literals are applied; a type error a
+// non-numeric `x` would have raised is the one behaviour lost.
+fn simplify_identity(expr: &mut Expr) {
+    let Expr::Binary(left, op, right) = expr else {
+        return;
+    };
+    let is = |expr: &Expr, literal: i64| *expr == Expr::NumberLiteral(literal, NumType::I32);
+    let keep_left = match op.as_str() {
+        "*" if is(right, 1) => true,
+        "*" if is(left, 1) => false,
+        "+" | "-" if is(right, 0) => true,
+        "+" if is(left, 0) => false,
+        _ => return,
+    };
+    if let Expr::Binary(left, _, right) = std::mem::replace(expr, Expr::BoolLiteral(false)) {
+        *expr = if keep_left { *left } else { *right };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::printer::pretty_print;
+
+    fn fold(source: &str) -> String {
+        let mut stmts = crate::parse_str(source).unwrap();
+        fold_stmts(&mut stmts);
+        pretty_print(&stmts)
+    }
+
+    #[test]
+    fn constant_arithmetic_folds() {
+        assert_eq!(fold("x = 2 + 3 * 4;"), "x = 14;\n");
+        assert_eq!(fold("if (x > 2 * 3) { y = -(1 + 1); }"), "if (x > 6) {\n    y = -2;\n}\n");
+    }
+
+    #[test]
+    fn expressions_with_variables_are_left_alone() {
+        assert_eq!(fold("x = a + 1;"), "x = a + 1;\n");
+        assert_eq!(fold("x = -5 + a * (2 - 1);"), "x = -5 + a * 1;\n");
+        assert_eq!(fold("x = 1 / 0;"), "x = 1 / 0;\n");
+    }
+
+    #[test]
+    fn identities_simplify() {
+        assert_eq!(optimize(crate::parse_str("x = speed * 1;").unwrap()), crate::parse_str("x = speed;").unwrap());
+        assert_eq!(
+            optimize(crate::parse_str("x = 1 * (speed + 0) - 0 + (0 + f(2 * 3));").unwrap()),
+            crate::parse_str("x = speed + f(6);").unwrap()
+        );
+    }
+
+    #[test]
+    fn calls_are_not_folded_away() {
+        assert_eq!(optimize(crate::parse_str("x = foo() * 0;").unwrap()), crate::parse_str("x = foo() * 0;").unwrap());
+    }
+}
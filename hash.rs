@@ -0,0 +1,70 @@
+use std::hash::{Hash, Hasher};
+
+use crate::parser::Stmt;
+
+// A structural hash of the program, for hosts caching compiled scripts.
+// Source positions, whitespace and comments do not affect it. FNV-1a with
+// integers fed little-endian keeps it stable across runs and platforms,
+// which `DefaultHasher` does not promise.
+pub fn ast_hash(stmts: &[Stmt]) -> u64 {
+    let mut hasher = Fnv1a(FNV_OFFSET_BASIS);
+    stmts.hash(&mut hasher);
+    hasher.finish()
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+struct Fnv1a(u64);
+
+impl Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn write_u16(&mut self, value: u16) {
+        self.write(&value.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, value: u32) {
+        self.write(&value.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.write(&value.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, value: usize) {
+        self.write_u64(value as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash(source: &str) -> u64 {
+        ast_hash(&crate::parse_str(source).unwrap())
+    }
+
+    #[test]
+    fn layout_and_comments_do_not_change_the_hash() {
+        assert_eq!(hash("x = a + 1.5; // c\nf(x);"), hash("x=a+1.5;\n\n  /* other */ f( x );"));
+        assert_eq!(hash("function f() {\n    return 1;\n}"), hash("function f() { return 1; }"));
+    }
+
+    #[test]
+    fn structure_changes_the_hash() {
+        assert_ne!(hash("x = a + 1;"), hash("x = a - 1;"));
+        assert_ne!(hash("x = 1; y = 2;"), hash("y = 2; x = 1;"));
+        assert_ne!(hash("f(a, b);"), hash("f(ab);"));
+        assert_ne!(hash("x = 1.5;"), hash("x = 1.25;"));
+        assert_ne!(hash("if (a) { f(); }"), hash("while (a) { f(); }"));
+    }
+}
@@ -0,0 +1,933 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use thiserror::Error;
+
+use crate::builtins;
+use crate::parser::{Expr, Stmt, StringPart};
+use crate::printer::print_expr;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    // Wide enough for any literal, whatever its suffix; arithmetic past
+    // the `i64` range is a `RuntimeError::Overflow`.
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Str(String),
+    Function(Rc<Closure>),
+    Unit,
+}
+
+// How a value reads when interpolated into a string.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Int(value) => write!(f, "{}", value),
+            Value::Float(value) => write!(f, "{}", value),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Str(value) => write!(f, "{}", value),
+            Value::Function(closure) => write!(f, "{:?}", closure),
+            Value::Unit => write!(f, "()"),
+        }
+    }
+}
+
+// A function value: its declaration plus the scope it was declared in, so
+// the body can read variables of enclosing functions after they return.
+pub struct Closure {
+    name: String,
+    params: Vec<(String, Option<Expr>)>,
+    body: Vec<Stmt>,
+    // None for functions declared at the top level, which see the globals.
+    env: Option<Rc<RefCell<Environment>>>,
+}
+
+// Closures compare by identity; their environments may be cyclic.
+impl PartialEq for Closure {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl fmt::Debug for Closure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let params: Vec<&str> = self.params.iter().map(|(param, _)| param.as_str()).collect();
+        write!(f, "<function {}({})>", self.name, params.join(", "))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum RuntimeError {
+    #[error("undefined variable {0}")]
+    UndefinedVariable(String),
+    // Assignment to a name never declared with `let` or `const`, in strict mode.
+    #[error("assignment to undeclared variable {0}")]
+    UndeclaredAssignment(String),
+    #[error("undefined function {0}")]
+    UndefinedFunction(String),
+    // Function name, expected and supplied argument counts.
+    #[error("{0} expects {1} arguments, got {2}")]
+    ArityMismatch(String, usize, usize),
+    #[error("type mismatch: {0}")]
+    TypeMismatch(String),
+    #[error("division by zero")]
+    DivisionByZero,
+    #[error("integer overflow")]
+    Overflow,
+    #[error("negative integer exponent {0}")]
+    NegativeExponent(i64),
+    #[error("unsupported: {0}")]
+    Unsupported(String),
+}
+
+pub type NativeFunction = Box<dyn Fn(&[Value]) -> Result<Value, RuntimeError>>;
+
+// Called before each statement runs with the variables visible to it,
+// sorted by name.
+pub type TraceHook = Box<dyn FnMut(&Stmt, &[(String, Value)])>;
+
+enum Flow {
+    Normal,
+    Break,
+    Continue,
+    Return(Value),
+}
+
+// One function call's variables, chained to the scope the function was
+// declared in. The outermost scope is the interpreter's globals.
+#[derive(Default)]
+struct Environment {
+    values: HashMap<String, Value>,
+    parent: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    fn child(parent: Option<Rc<RefCell<Environment>>>) -> Rc<RefCell<Environment>> {
+        Rc::new(RefCell::new(Environment {
+            values: HashMap::new(),
+            parent,
+        }))
+    }
+
+    fn get(&self, name: &str) -> Option<Value> {
+        match self.values.get(name) {
+            Some(value) => Some(value.clone()),
+            None => self.parent.as_ref()?.borrow().get(name),
+        }
+    }
+
+    // Adds the bindings of this chain not shadowed by an inner scope.
+    fn collect(&self, into: &mut HashMap<String, Value>) {
+        for (name, value) in &self.values {
+            into.entry(name.clone()).or_insert_with(|| value.clone());
+        }
+        if let Some(parent) = &self.parent {
+            parent.borrow().collect(into);
+        }
+    }
+
+    // Updates an existing binding, returning the value back if the name is
+    // not bound in this chain.
+    fn assign(&mut self, name: &str, value: Value) -> Result<(), Value> {
+        if let Some(slot) = self.values.get_mut(name) {
+            *slot = value;
+            return Ok(());
+        }
+        match &self.parent {
+            Some(parent) => parent.borrow_mut().assign(name, value),
+            None => Err(value),
+        }
+    }
+}
+
+pub struct Interpreter {
+    globals: HashMap<String, Value>,
+    // The innermost scope of the running function; None at the top level.
+    env: Option<Rc<RefCell<Environment>>>,
+    handlers: HashMap<String, Rc<Closure>>,
+    natives: HashMap<String, NativeFunction>,
+    strict: bool,
+    trace: Option<TraceHook>,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        let mut interpreter = Interpreter {
+            globals: HashMap::new(),
+            env: None,
+            handlers: HashMap::new(),
+            natives: HashMap::new(),
+            strict: false,
+            trace: None,
+        };
+        interpreter.register_native("log", builtins::log_native(|message| println!("{}", message)));
+        interpreter.register_native("typeof", builtins::typeof_native);
+        interpreter
+    }
+
+    // Sends `log(...)` messages to `sink` instead of stdout.
+    pub fn with_log_sink(mut self, sink: impl Fn(&str) + 'static) -> Self {
+        self.register_native("log", builtins::log_native(sink));
+        self
+    }
+
+    pub fn with_strict_mode(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    pub fn with_trace_hook(mut self, hook: impl FnMut(&Stmt, &[(String, Value)]) + 'static) -> Self {
+        self.trace = Some(Box::new(hook));
+        self
+    }
+
+    pub fn register_native(&mut self, name: &str, function: impl Fn(&[Value]) -> Result<Value, RuntimeError> + 'static) {
+        self.natives.insert(name.to_string(), Box::new(function));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.globals.get(name)
+    }
+
+    pub fn run(&mut self, stmts: &[Stmt]) -> Result<(), RuntimeError> {
+        self.eval_block(stmts)?;
+        Ok(())
+    }
+
+    pub fn call(&mut self, name: &str, args: Vec<Value>) -> Result<Value, RuntimeError> {
+        match self.lookup(name) {
+            Some(Value::Function(closure)) => return self.call_closure(&closure, args),
+            Some(other) => return Err(RuntimeError::TypeMismatch(format!("{} is not a function: {:?}", name, other))),
+            None => {}
+        }
+        match self.natives.get(name) {
+            Some(native) => native(&args),
+            None => Err(RuntimeError::UndefinedFunction(name.to_string())),
+        }
+    }
+
+    // Runs the handler registered with `on <event> { ... }`; events nobody
+    // listens for are ignored.
+    pub fn dispatch(&mut self, event: &str) -> Result<(), RuntimeError> {
+        if let Some(handler) = self.handlers.get(event).cloned() {
+            self.call_closure(&handler, Vec::new())?;
+        }
+        Ok(())
+    }
+
+    fn call_closure(&mut self, closure: &Closure, args: Vec<Value>) -> Result<Value, RuntimeError> {
+        let required = closure.params.iter().filter(|(_, default)| default.is_none()).count();
+        if args.len() < required || args.len() > closure.params.len() {
+            let expected = if args.len() < required { required } else { closure.params.len() };
+            return Err(RuntimeError::ArityMismatch(closure.name.clone(), expected, args.len()));
+        }
+        let caller = self.env.replace(Environment::child(closure.env.clone()));
+        let result = self.bind_params(&closure.params, args).and_then(|()| self.eval_block(&closure.body));
+        self.env = caller;
+        match result? {
+            Flow::Return(value) => Ok(value),
+            _ => Ok(Value::Unit),
+        }
+    }
+
+    // Defaults are evaluated in the callee's scope, so they can refer to the
+    // parameters before them.
+    fn bind_params(&mut self, params: &[(String, Option<Expr>)], args: Vec<Value>) -> Result<(), RuntimeError> {
+        let mut args = args.into_iter();
+        for (param, default) in params {
+            let value = match (args.next(), default) {
+                (Some(arg), _) => arg,
+                (None, Some(default)) => self.eval_expr(default)?,
+                (None, None) => unreachable!("arity checked by the caller"),
+            };
+            self.define(param, value);
+        }
+        Ok(())
+    }
+
+    fn lookup(&self, name: &str) -> Option<Value> {
+        match &self.env {
+            Some(env) => env.borrow().get(name).or_else(|| self.globals.get(name).cloned()),
+            None => self.globals.get(name).cloned(),
+        }
+    }
+
+    fn define(&mut self, name: &str, value: Value) {
+        match &self.env {
+            Some(env) => {
+                env.borrow_mut().values.insert(name.to_string(), value);
+            }
+            None => {
+                self.globals.insert(name.to_string(), value);
+            }
+        }
+    }
+
+    // Assigning a name that is not bound in any enclosing scope creates a
+    // global, as `speed = 100;` inside a function always has, unless the
+    // interpreter is strict.
+    fn assign(&mut self, name: &str, value: Value) -> Result<(), RuntimeError> {
+        let value = match &self.env {
+            Some(env) => match env.borrow_mut().assign(name, value) {
+                Ok(()) => return Ok(()),
+                Err(value) => value,
+            },
+            None => value,
+        };
+        if self.strict && !self.globals.contains_key(name) {
+            return Err(RuntimeError::UndeclaredAssignment(name.to_string()));
+        }
+        self.globals.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    fn eval_block(&mut self, stmts: &[Stmt]) -> Result<Flow, RuntimeError> {
+        for stmt in stmts {
+            match self.eval_stmt(stmt)? {
+                Flow::Normal => {}
+                flow => return Ok(flow),
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn trace(&mut self, stmt: &Stmt) {
+        if self.trace.is_none() {
+            return;
+        }
+        let mut visible = HashMap::new();
+        if let Some(env) = &self.env {
+            env.borrow().collect(&mut visible);
+        }
+        for (name, value) in &self.globals {
+            visible.entry(name.clone()).or_insert_with(|| value.clone());
+        }
+        let mut snapshot: Vec<(String, Value)> = visible.into_iter().collect();
+        snapshot.sort_by(|a, b| a.0.cmp(&b.0));
+        if let Some(hook) = &mut self.trace {
+            hook(stmt, &snapshot);
+        }
+    }
+
+    fn eval_stmt(&mut self, stmt: &Stmt) -> Result<Flow, RuntimeError> {
+        self.trace(stmt);
+        match stmt {
+            Stmt::VarDecl(name, expr) => {
+                let value = match expr {
+                    Some(expr) => self.eval_expr(expr)?,
+                    None => Value::Unit,
+                };
+                self.define(name, value);
+                Ok(Flow::Normal)
+            }
+            Stmt::ConstDecl(name, expr) => {
+                let value = self.eval_expr(expr)?;
+                self.define(name, value);
+                Ok(Flow::Normal)
+            }
+            Stmt::Assignment(name, expr) => {
+                let value = self.eval_expr(expr)?;
+                self.assign(name, value)?;
+                Ok(Flow::Normal)
+            }
+            // `i++` runs as `i = i + 1`.
+            Stmt::Increment(name, op) => {
+                let current = self.lookup(name).ok_or_else(|| RuntimeError::UndefinedVariable(name.clone()))?;
+                let value = eval_binary(current, &op[..1], Value::Int(1))?;
+                self.assign(name, value)?;
+                Ok(Flow::Normal)
+            }
+            Stmt::CompoundAssignment(name, op, expr) => {
+                let current = self.lookup(name).ok_or_else(|| RuntimeError::UndefinedVariable(name.clone()))?;
+                let value = self.eval_expr(expr)?;
+                let value = eval_binary(current, op.trim_end_matches('='), value)?;
+                self.assign(name, value)?;
+                Ok(Flow::Normal)
+            }
+            Stmt::FunctionDeclaration(name, params, body, _) => {
+                let closure = self.closure(name, params, body);
+                self.define(name, Value::Function(closure));
+                Ok(Flow::Normal)
+            }
+            Stmt::If(condition, body, else_body) => {
+                if self.eval_condition(condition)? {
+                    self.eval_block(body)
+                } else if let Some(else_body) = else_body {
+                    self.eval_block(else_body)
+                } else {
+                    Ok(Flow::Normal)
+                }
+            }
+            Stmt::IfChain(arms, else_body) => {
+                for (condition, body) in arms {
+                    if self.eval_condition(condition)? {
+                        return self.eval_block(body);
+                    }
+                }
+                match else_body {
+                    Some(else_body) => self.eval_block(else_body),
+                    None => Ok(Flow::Normal),
+                }
+            }
+            Stmt::Switch(subject, cases, default) => {
+                let subject = self.eval_expr(subject)?;
+                for (value, body) in cases {
+                    let value = self.eval_expr(value)?;
+                    if eval_binary(subject.clone(), "==", value)? == Value::Bool(true) {
+                        return self.eval_block(body);
+                    }
+                }
+                match default {
+                    Some(default) => self.eval_block(default),
+                    None => Ok(Flow::Normal),
+                }
+            }
+            Stmt::While(condition, body) => {
+                while self.eval_condition(condition)? {
+                    match self.eval_block(body)? {
+                        Flow::Break => break,
+                        Flow::Normal | Flow::Continue => {}
+                        Flow::Return(value) => return Ok(Flow::Return(value)),
+                    }
+                }
+                Ok(Flow::Normal)
+            }
+            Stmt::ForRange(name, range, body) => {
+                let (start, end) = self.eval_range(range)?;
+                for i in start..end {
+                    let scope = Environment::child(self.env.clone());
+                    scope.borrow_mut().values.insert(name.clone(), Value::Int(i));
+                    let outer = self.env.replace(scope);
+                    let flow = self.eval_block(body);
+                    self.env = outer;
+                    match flow? {
+                        Flow::Break => break,
+                        Flow::Normal | Flow::Continue => {}
+                        Flow::Return(value) => return Ok(Flow::Return(value)),
+                    }
+                }
+                Ok(Flow::Normal)
+            }
+            Stmt::ForClassic(init, condition, step, body) => {
+                let scope = Environment::child(self.env.clone());
+                let outer = self.env.replace(scope);
+                let flow = self.eval_classic_for(init, condition, step, body);
+                self.env = outer;
+                flow
+            }
+            Stmt::Block(body) => {
+                let scope = Environment::child(self.env.clone());
+                let outer = self.env.replace(scope);
+                let result = self.eval_block(body);
+                self.env = outer;
+                result
+            }
+            Stmt::Break => Ok(Flow::Break),
+            Stmt::Import(path) => Err(RuntimeError::Unsupported(format!("unresolved import {}", path))),
+            Stmt::Continue => Ok(Flow::Continue),
+            Stmt::Return(expr) => {
+                let value = match expr {
+                    Some(expr) => self.eval_expr(expr)?,
+                    None => Value::Unit,
+                };
+                Ok(Flow::Return(value))
+            }
+            Stmt::EventHandler(event, body) => {
+                let handler = self.closure(event, &[], body);
+                self.handlers.insert(event.clone(), handler);
+                Ok(Flow::Normal)
+            }
+            Stmt::Expression(expr) => {
+                self.eval_expr(expr)?;
+                Ok(Flow::Normal)
+            }
+        }
+    }
+
+    fn closure(&self, name: &str, params: &[(String, Option<Expr>)], body: &[Stmt]) -> Rc<Closure> {
+        Rc::new(Closure {
+            name: name.to_string(),
+            params: params.to_vec(),
+            body: body.to_vec(),
+            env: self.env.clone(),
+        })
+    }
+
+    fn eval_condition(&mut self, condition: &Expr) -> Result<bool, RuntimeError> {
+        match self.eval_expr(condition)? {
+            Value::Bool(value) => Ok(value),
+            other => Err(RuntimeError::TypeMismatch(format!("condition must be a bool, found {:?}", other))),
+        }
+    }
+
+    fn eval_operand(&mut self, operand: &Expr, op: &str) -> Result<bool, RuntimeError> {
+        match self.eval_expr(operand)? {
+            Value::Bool(value) => Ok(value),
+            other => Err(RuntimeError::TypeMismatch(format!("cannot apply {} to {:?}", op, other))),
+        }
+    }
+
+    fn eval_classic_for(&mut self, init: &Stmt, condition: &Expr, step: &Stmt, body: &[Stmt]) -> Result<Flow, RuntimeError> {
+        self.eval_stmt(init)?;
+        while self.eval_condition(condition)? {
+            match self.eval_block(body)? {
+                Flow::Break => break,
+                Flow::Normal | Flow::Continue => {}
+                Flow::Return(value) => return Ok(Flow::Return(value)),
+            }
+            self.eval_stmt(step)?;
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn eval_range(&mut self, range: &Expr) -> Result<(i64, i64), RuntimeError> {
+        let Expr::Range(start, end) = range else {
+            return Err(RuntimeError::TypeMismatch(format!("cannot iterate over {}", print_expr(range))));
+        };
+        match (self.eval_expr(start)?, self.eval_expr(end)?) {
+            (Value::Int(start), Value::Int(end)) => Ok((start, end)),
+            (start, end) => Err(RuntimeError::TypeMismatch(format!("range bounds must be ints, found {:?} and {:?}", start, end))),
+        }
+    }
+
+    fn eval_expr(&mut self, expr: &Expr) -> Result<Value, RuntimeError> {
+        match expr {
+            Expr::NumberLiteral(value, _) => Ok(Value::Int(*value)),
+            Expr::BoolLiteral(value) => Ok(Value::Bool(*value)),
+            Expr::FloatLiteral(value) => Ok(Value::Float(*value)),
+            Expr::StringLiteral(value) => Ok(Value::Str(value.clone())),
+            Expr::Quantity(..) => Err(RuntimeError::Unsupported("quantity literals".to_string())),
+            Expr::Variable(name, _) => self.lookup(name).ok_or_else(|| RuntimeError::UndefinedVariable(name.clone())),
+            Expr::Unary(op, operand) => {
+                let operand = self.eval_expr(operand)?;
+                eval_unary(op, operand)
+            }
+            // The right operand is only evaluated when it decides the result.
+            Expr::Binary(left, op, right) if op == "&&" || op == "||" => {
+                let short_circuit = op == "||";
+                if self.eval_operand(left, op)? == short_circuit {
+                    return Ok(Value::Bool(short_circuit));
+                }
+                Ok(Value::Bool(self.eval_operand(right, op)?))
+            }
+            Expr::Binary(left, op, right) => {
+                let left = self.eval_expr(left)?;
+                let right = self.eval_expr(right)?;
+                eval_binary(left, op, right)
+            }
+            Expr::Ternary(condition, then_branch, else_branch) => {
+                if self.eval_condition(condition)? {
+                    self.eval_expr(then_branch)
+                } else {
+                    self.eval_expr(else_branch)
+                }
+            }
+            Expr::Assign(name, value, _) => {
+                let value = self.eval_expr(value)?;
+                self.assign(name, value.clone())?;
+                Ok(value)
+            }
+            Expr::ArrayLiteral(_) | Expr::Index(..) => Err(RuntimeError::Unsupported("arrays".to_string())),
+            Expr::Range(..) => Err(RuntimeError::Unsupported("ranges outside a for loop".to_string())),
+            Expr::Call(name, args) => {
+                let mut values = Vec::new();
+                for arg in args {
+                    values.push(self.eval_expr(arg)?);
+                }
+                self.call(name, values)
+            }
+            Expr::Interpolation(parts) => {
+                let mut out = String::new();
+                for part in parts {
+                    match part {
+                        StringPart::Literal(text) => out.push_str(text),
+                        StringPart::Expr(expr) => out.push_str(&self.eval_expr(expr)?.to_string()),
+                    }
+                }
+                Ok(Value::Str(out))
+            }
+        }
+    }
+}
+
+pub(crate) fn eval_unary(op: &str, operand: Value) -> Result<Value, RuntimeError> {
+    match (op, operand) {
+        ("-", Value::Int(value)) => value.checked_neg().map(Value::Int).ok_or(RuntimeError::Overflow),
+        ("-", Value::Float(value)) => Ok(Value::Float(-value)),
+        ("!", Value::Bool(value)) => Ok(Value::Bool(!value)),
+        (op, operand) => Err(RuntimeError::TypeMismatch(format!("cannot apply {} to {:?}", op, operand))),
+    }
+}
+
+// Mixed int and float operands are promoted to float.
+pub(crate) fn eval_binary(left: Value, op: &str, right: Value) -> Result<Value, RuntimeError> {
+    match (left, right) {
+        (Value::Int(l), Value::Float(r)) => eval_float_binary(l as f64, op, r),
+        (Value::Float(l), Value::Int(r)) => eval_float_binary(l, op, r as f64),
+        (Value::Float(l), Value::Float(r)) => eval_float_binary(l, op, r),
+        (Value::Int(l), Value::Int(r)) => match op {
+            "/" | "%" if r == 0 => Err(RuntimeError::DivisionByZero),
+            "+" => l.checked_add(r).map(Value::Int).ok_or(RuntimeError::Overflow),
+            "-" => l.checked_sub(r).map(Value::Int).ok_or(RuntimeError::Overflow),
+            "*" => l.checked_mul(r).map(Value::Int).ok_or(RuntimeError::Overflow),
+            "/" => l.checked_div(r).map(Value::Int).ok_or(RuntimeError::Overflow),
+            "%" => l.checked_rem(r).map(Value::Int).ok_or(RuntimeError::Overflow),
+            "**" => match u32::try_from(r) {
+                Ok(exponent) => l.checked_pow(exponent).map(Value::Int).ok_or(RuntimeError::Overflow),
+                Err(_) => Err(RuntimeError::NegativeExponent(r)),
+            },
+            "==" => Ok(Value::Bool(l == r)),
+            "!=" => Ok(Value::Bool(l != r)),
+            "<" => Ok(Value::Bool(l < r)),
+            ">" => Ok(Value::Bool(l > r)),
+            "<=" => Ok(Value::Bool(l <= r)),
+            ">=" => Ok(Value::Bool(l >= r)),
+            _ => Err(RuntimeError::Unsupported(format!("operator {} on ints", op))),
+        },
+        (Value::Bool(l), Value::Bool(r)) => match op {
+            "&&" => Ok(Value::Bool(l && r)),
+            "||" => Ok(Value::Bool(l || r)),
+            "==" => Ok(Value::Bool(l == r)),
+            "!=" => Ok(Value::Bool(l != r)),
+            _ => Err(RuntimeError::Unsupported(format!("operator {} on bools", op))),
+        },
+        (Value::Str(l), Value::Str(r)) => match op {
+            "+" => Ok(Value::Str(l + &r)),
+            "==" => Ok(Value::Bool(l == r)),
+            "!=" => Ok(Value::Bool(l != r)),
+            _ => Err(RuntimeError::Unsupported(format!("operator {} on strings", op))),
+        },
+        (l, r) => Err(RuntimeError::TypeMismatch(format!("cannot apply {} to {:?} and {:?}", op, l, r))),
+    }
+}
+
+fn eval_float_binary(l: f64, op: &str, r: f64) -> Result<Value, RuntimeError> {
+    match op {
+        "+" => Ok(Value::Float(l + r)),
+        "-" => Ok(Value::Float(l - r)),
+        "*" => Ok(Value::Float(l * r)),
+        "/" | "%" if r == 0.0 => Err(RuntimeError::DivisionByZero),
+        "/" => Ok(Value::Float(l / r)),
+        "%" => Ok(Value::Float(l % r)),
+        "**" => Ok(Value::Float(l.powf(r))),
+        "==" => Ok(Value::Bool(l == r)),
+        "!=" => Ok(Value::Bool(l != r)),
+        "<" => Ok(Value::Bool(l < r)),
+        ">" => Ok(Value::Bool(l > r)),
+        "<=" => Ok(Value::Bool(l <= r)),
+        ">=" => Ok(Value::Bool(l >= r)),
+        _ => Err(RuntimeError::Unsupported(format!("operator {} on floats", op))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn run(source: &str) -> Result<Interpreter, RuntimeError> {
+        let mut interpreter = Interpreter::new();
+        interpreter.run(&crate::parse_str(source).unwrap())?;
+        Ok(interpreter)
+    }
+
+    fn eval(expr: &str) -> Result<Value, RuntimeError> {
+        let interpreter = run(&format!("x = {};", expr))?;
+        Ok(interpreter.get("x").cloned().unwrap())
+    }
+
+    #[test]
+    fn mixed_arithmetic_promotes_to_float() {
+        assert_eq!(eval("2 + 1.5"), Ok(Value::Float(3.5)));
+        assert_eq!(eval("2 + 1.5 == 3.5"), Ok(Value::Bool(true)));
+        assert_eq!(eval("3 * 0.5"), Ok(Value::Float(1.5)));
+        assert_eq!(eval("7 / 2"), Ok(Value::Int(3)));
+    }
+
+    #[test]
+    fn ints_and_floats_compare() {
+        assert_eq!(eval("2 < 2.5"), Ok(Value::Bool(true)));
+        assert_eq!(eval("3.0 == 3"), Ok(Value::Bool(true)));
+        assert_eq!(eval("0x10 >= 16.5"), Ok(Value::Bool(false)));
+    }
+
+    #[test]
+    fn ints_are_64_bits() {
+        assert_eq!(eval("100i64"), Ok(Value::Int(100)));
+        assert_eq!(eval("2000000000 + 2000000000"), Ok(Value::Int(4_000_000_000)));
+        assert_eq!(eval("9223372036854775807i64"), Ok(Value::Int(i64::MAX)));
+        assert_eq!(eval("9223372036854775807i64 + 1"), Err(RuntimeError::Overflow));
+        assert_eq!(eval("2 ** 63"), Err(RuntimeError::Overflow));
+    }
+
+    #[test]
+    fn division_by_zero_is_an_error() {
+        assert_eq!(eval("1 / 0"), Err(RuntimeError::DivisionByZero));
+        assert_eq!(eval("1 % 0"), Err(RuntimeError::DivisionByZero));
+        assert_eq!(eval("1.5 / 0.0"), Err(RuntimeError::DivisionByZero));
+    }
+
+    #[test]
+    fn runs_the_readme_program() {
+        let source = "
+        function startEngine() {
+            speed = 100;
+            if (speed > 60) {
+                applyBrakes();
+            }
+        }
+    ";
+        let fired = Rc::new(Cell::new(false));
+        let mut interpreter = Interpreter::new();
+        let brakes = fired.clone();
+        interpreter.register_native("applyBrakes", move |_| {
+            brakes.set(true);
+            Ok(Value::Unit)
+        });
+        interpreter.run(&crate::parse_str(source).unwrap()).unwrap();
+        interpreter.call("startEngine", vec![]).unwrap();
+        assert_eq!(interpreter.get("speed"), Some(&Value::Int(100)));
+        assert!(fired.get());
+    }
+
+    #[test]
+    fn functions_return_values_and_loops_run() {
+        let interpreter = run("function f(a) { return a * 2; } x = f(4) + 1; while (x > 3) { x = x - 1; }").unwrap();
+        assert_eq!(interpreter.get("x"), Some(&Value::Int(3)));
+    }
+
+    #[test]
+    fn dispatching_an_event_runs_its_handler() {
+        let mut interpreter = run("speed = 5; on collision { speed = 0; }").unwrap();
+        assert_eq!(interpreter.get("speed"), Some(&Value::Int(5)));
+        interpreter.dispatch("collision").unwrap();
+        interpreter.dispatch("other").unwrap();
+        assert_eq!(interpreter.get("speed"), Some(&Value::Int(0)));
+    }
+
+    #[test]
+    fn modulo_is_the_integer_remainder() {
+        assert_eq!(eval("370 % 360 == 10"), Ok(Value::Bool(true)));
+        assert_eq!(eval("-7 % 3"), Ok(Value::Int(-1)));
+    }
+
+    #[test]
+    fn ternary_evaluates_only_the_chosen_branch() {
+        assert_eq!(run("fast = true; mode = fast ? 2 : 1;").unwrap().get("mode"), Some(&Value::Int(2)));
+        assert_eq!(run("fast = false; mode = fast ? 2 : 1;").unwrap().get("mode"), Some(&Value::Int(1)));
+        assert_eq!(eval("false ? nope() : 1"), Ok(Value::Int(1)));
+    }
+
+    #[test]
+    fn break_leaves_the_loop() {
+        let interpreter = run("i = 0; while (true) { i += 1; if (i == 5) { break; } }").unwrap();
+        assert_eq!(interpreter.get("i"), Some(&Value::Int(5)));
+    }
+
+    #[test]
+    fn continue_skips_the_rest_of_the_body() {
+        let interpreter = run("i = 0; n = 0; while (i < 10) { i += 1; if (i % 2 == 0) { continue; } n += 1; }").unwrap();
+        assert_eq!(interpreter.get("n"), Some(&Value::Int(5)));
+    }
+
+    #[test]
+    fn assignment_expression_returns_the_value() {
+        let interpreter = run("a = (b = 3) + 1;").unwrap();
+        assert_eq!(interpreter.get("a"), Some(&Value::Int(4)));
+        assert_eq!(interpreter.get("b"), Some(&Value::Int(3)));
+    }
+
+    #[test]
+    fn inner_function_reads_outer_variable() {
+        let interpreter = run("function outer() { let base = 10; function inner(x) { return base + x; } return inner(5); } r = outer();").unwrap();
+        assert_eq!(interpreter.get("r"), Some(&Value::Int(15)));
+        assert_eq!(interpreter.get("base"), None);
+    }
+
+    #[test]
+    fn closures_keep_their_own_environment() {
+        let interpreter = run("
+            function counter() { let n = 0; function next() { n += 1; return n; } return next; }
+            c = counter(); c(); a = c();
+            d = counter(); b = d();
+        ")
+        .unwrap();
+        assert!(matches!(interpreter.get("c"), Some(Value::Function(_))));
+        assert_eq!((interpreter.get("a"), interpreter.get("b")), (Some(&Value::Int(2)), Some(&Value::Int(1))));
+    }
+
+    #[test]
+    fn strict_mode_rejects_undeclared_assignment() {
+        let ast = crate::parse_str("function startEngine() { speed = 100; } startEngine();").unwrap();
+        assert!(Interpreter::new().run(&ast).is_ok());
+        assert_eq!(Interpreter::new().with_strict_mode().run(&ast), Err(RuntimeError::UndeclaredAssignment("speed".into())));
+        let ast = crate::parse_str("let a = (b = 1);").unwrap();
+        assert_eq!(Interpreter::new().with_strict_mode().run(&ast), Err(RuntimeError::UndeclaredAssignment("b".into())));
+        let mut interpreter = Interpreter::new().with_strict_mode();
+        interpreter.run(&crate::parse_str("let speed = 0; speed = 100;").unwrap()).unwrap();
+        assert_eq!(interpreter.get("speed"), Some(&Value::Int(100)));
+    }
+
+    #[test]
+    fn block_locals_do_not_leak() {
+        let interpreter = run("let x = 1; { let t = 5; x = x + t; y = t; }").unwrap();
+        assert_eq!(interpreter.get("x"), Some(&Value::Int(6)));
+        assert_eq!(interpreter.get("y"), Some(&Value::Int(5)));
+        assert_eq!(interpreter.get("t"), None);
+        assert_eq!(run("{ let t = 1; } x = t;").err(), Some(RuntimeError::UndefinedVariable("t".into())));
+    }
+
+    #[test]
+    fn overflow_is_an_error_not_a_wrap() {
+        assert_eq!(eval("9223372036854775807i64 * 2"), Err(RuntimeError::Overflow));
+        assert_eq!(eval("-9223372036854775807i64 - 2"), Err(RuntimeError::Overflow));
+        assert_eq!(run("x = -9223372036854775807i64 - 1; y = -x;").err(), Some(RuntimeError::Overflow));
+    }
+
+    #[test]
+    fn if_chain_runs_the_first_true_arm() {
+        let chain = "if (a) { x = 1; } else if (b) { x = 2; } else unless (c) { x = 3; } else { x = 4; }";
+        for (flags, arm) in [("a = true; b = true; c = true;", 1), ("a = false; b = true; c = true;", 2), ("a = false; b = false; c = false;", 3), ("a = false; b = false; c = true;", 4)] {
+            let interpreter = run(&format!("{} {}", flags, chain)).unwrap();
+            assert_eq!(interpreter.get("x"), Some(&Value::Int(arm)), "{}", flags);
+        }
+    }
+
+    #[test]
+    fn switch_runs_the_matching_case() {
+        let switch = "switch (driveMode) { case 1 { x = \"eco\"; } case 2 { x = \"sport\"; } default { x = \"normal\"; } }";
+        for (mode, selected) in [(1, "eco"), (2, "sport"), (7, "normal")] {
+            let interpreter = run(&format!("driveMode = {}; {}", mode, switch)).unwrap();
+            assert_eq!(interpreter.get("x"), Some(&Value::Str(selected.into())));
+        }
+        let interpreter = run("m = 3; x = 0; switch (m) { case 1 { x = 1; } }").unwrap();
+        assert_eq!(interpreter.get("x"), Some(&Value::Int(0)));
+    }
+
+    #[test]
+    fn logical_operators_short_circuit() {
+        let calls = Rc::new(Cell::new(0));
+        let mut interpreter = Interpreter::new();
+        let counter = calls.clone();
+        interpreter.register_native("sideEffect", move |_| {
+            counter.set(counter.get() + 1);
+            Ok(Value::Bool(false))
+        });
+        let source = "a = false && sideEffect(); b = true || sideEffect(); c = true && sideEffect(); d = false || sideEffect();";
+        interpreter.run(&crate::parse_str(source).unwrap()).unwrap();
+        assert_eq!(calls.get(), 2);
+        for name in ["a", "c", "d"] {
+            assert_eq!(interpreter.get(name), Some(&Value::Bool(false)), "{}", name);
+        }
+        assert_eq!(interpreter.get("b"), Some(&Value::Bool(true)));
+    }
+
+    #[test]
+    fn comparisons_are_bools() {
+        assert_eq!(eval("2 > 1.5"), Ok(Value::Bool(true)));
+        assert_eq!(eval("1 == 1"), Ok(Value::Bool(true)));
+        assert!(matches!(eval("true && 1"), Err(RuntimeError::TypeMismatch(_))));
+    }
+
+    #[test]
+    fn omitted_arguments_take_their_default() {
+        let brake = "function brake(force = 50, ramp = force / 10) { applied = force; r = ramp; }";
+        for (call, force, ramp) in [("brake();", 50, 5), ("brake(80);", 80, 8), ("brake(80, 1);", 80, 1)] {
+            let interpreter = run(&format!("{} {}", brake, call)).unwrap();
+            assert_eq!((interpreter.get("applied"), interpreter.get("r")), (Some(&Value::Int(force)), Some(&Value::Int(ramp))), "{}", call);
+        }
+        assert_eq!(run("function f(a, b = 1) { } f();").err(), Some(RuntimeError::ArityMismatch("f".into(), 1, 0)));
+        assert_eq!(run("function f(a, b = 1) { } f(1, 2, 3);").err(), Some(RuntimeError::ArityMismatch("f".into(), 2, 3)));
+    }
+
+    #[test]
+    fn trace_hook_sees_each_statement_in_order() {
+        let source = "
+        function startEngine() {
+            speed = 100;
+            if (speed > 60) {
+                applyBrakes();
+            }
+        }
+        startEngine();
+    ";
+        // The first line of each statement and the variables it saw.
+        type Trace = Vec<(String, Vec<String>)>;
+        let trace: Rc<RefCell<Trace>> = Rc::default();
+        let recorder = trace.clone();
+        let mut interpreter = Interpreter::new().with_trace_hook(move |stmt, vars| {
+            let first_line = crate::printer::pretty_print(std::slice::from_ref(stmt)).lines().next().unwrap().to_string();
+            let vars = vars.iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+            recorder.borrow_mut().push((first_line, vars));
+        });
+        interpreter.register_native("applyBrakes", |_| Ok(Value::Unit));
+        interpreter.run(&crate::parse_str(source).unwrap()).unwrap();
+        let trace = trace.borrow();
+        let stmts: Vec<_> = trace.iter().map(|(stmt, _)| stmt.as_str()).collect();
+        assert_eq!(stmts, ["function startEngine() {", "startEngine();", "speed = 100;", "if (speed > 60) {", "applyBrakes();"]);
+        assert!(trace[0].1.is_empty());
+        assert_eq!(trace[3].1[0], "speed=100");
+    }
+
+    #[test]
+    fn power_of_ints_and_floats() {
+        assert_eq!(eval("2 ** 3 ** 2"), Ok(Value::Int(512)));
+        assert_eq!(eval("2.0 ** 0.5"), Ok(Value::Float(2f64.sqrt())));
+        assert_eq!(eval("2 ** -1"), Err(RuntimeError::NegativeExponent(-1)));
+    }
+
+    #[test]
+    fn log_writes_to_the_sink() {
+        let logged: Rc<RefCell<Vec<String>>> = Rc::default();
+        let sink = logged.clone();
+        let mut interpreter = Interpreter::new().with_log_sink(move |message| sink.borrow_mut().push(message.to_string()));
+        interpreter.run(&crate::parse_str("log(\"hi\"); log(\"speed \" + \"ok\");").unwrap()).unwrap();
+        assert_eq!(*logged.borrow(), ["hi", "speed ok"]);
+        assert!(matches!(interpreter.run(&crate::parse_str("log(1);").unwrap()), Err(RuntimeError::TypeMismatch(_))));
+    }
+
+    #[test]
+    fn interpolation_substitutes_values() {
+        let interpreter = run("function f(s) { return s; } speed = 42; m = \"speed=${speed}, ${f(\"}\") + \"${\"x\"}\"} ok=${speed > 40} ${1.5}\";").unwrap();
+        assert_eq!(interpreter.get("m"), Some(&Value::Str("speed=42, }x ok=true 1.5".into())));
+        assert_eq!(eval("\"a${1}\\${2}\""), Ok(Value::Str("a1${2}".into())));
+    }
+
+    #[test]
+    fn for_in_runs_once_per_value() {
+        let interpreter = run("n = 0; s = 0; for (i in 0..3) { n += 1; s += i; }").unwrap();
+        assert_eq!((interpreter.get("n"), interpreter.get("s"), interpreter.get("i")), (Some(&Value::Int(3)), Some(&Value::Int(3)), None));
+        assert_eq!(run("c = 0; for (i in 3..0) { c += 1; }").unwrap().get("c"), Some(&Value::Int(0)));
+    }
+
+    #[test]
+    fn classic_for_accumulates() {
+        let interpreter = run("sum = 0; for (let i = 0; i < 5; i += 1) { sum += i; }").unwrap();
+        assert_eq!((interpreter.get("sum"), interpreter.get("i")), (Some(&Value::Int(10)), None));
+        let interpreter = run("after = 0; for (k = 10; true; k = k - 1) { if (k < 8) { break; } after += k; }").unwrap();
+        assert_eq!((interpreter.get("after"), interpreter.get("k")), (Some(&Value::Int(27)), Some(&Value::Int(7))));
+    }
+
+    #[test]
+    fn ternary_skips_the_branch_not_taken() {
+        let interpreter = run("n = 0; function bump() { n = n + 1; return n; } a = true ? 1 : bump(); b = false ? bump() : 2;").unwrap();
+        assert_eq!((interpreter.get("n"), interpreter.get("a"), interpreter.get("b")), (Some(&Value::Int(0)), Some(&Value::Int(1)), Some(&Value::Int(2))));
+    }
+
+    #[test]
+    fn increments_add_or_subtract_one() {
+        let interpreter = run("i = 5; i++; i++; j = 3; j--; f = 1.5; f++;").unwrap();
+        assert_eq!((interpreter.get("i"), interpreter.get("j"), interpreter.get("f")), (Some(&Value::Int(7)), Some(&Value::Int(2)), Some(&Value::Float(2.5))));
+        assert_eq!(run("n = 0; for (i = 0; i < 4; i++) { n += i; }").unwrap().get("n"), Some(&Value::Int(6)));
+        assert!(matches!(run("k++;").err(), Some(RuntimeError::UndefinedVariable(_))));
+    }
+}
@@ -1,102 +1,1065 @@
 use regex::Regex;
+use std::fmt;
 use std::str::FromStr;
+use thiserror::Error;
 
-#[derive(Debug, PartialEq)]
+#[cfg(feature = "schema")]
+use schemars::JsonSchema;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// Longer suffixes first so `mph` is not lexed as `m` followed by `ph`.
+const UNIT_SUFFIXES: &[&str] = &["km/h", "mph", "bar", "m", "s"];
+
+// Width suffixes of integer literals, kept in the number token.
+const INT_SUFFIXES: &[&str] = &["i8", "i16", "i32", "i64", "u8", "u16", "u32", "u64"];
+
+// Enough for any real script while bounding what a runaway input can
+// allocate.
+const DEFAULT_MAX_TOKENS: usize = 1_000_000;
+
+const KEYWORDS: &[&str] = &[
+    "function", "if", "unless", "else", "while", "break", "continue", "return", "let", "const", "on", "true", "false",
+    "switch", "case", "default", "for", "in", "pure", "import",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "kind", content = "value"))]
 pub enum TokenType {
     Keyword,
     Identifier,
     Number,
+    Quantity,
+    String,
     Operator,
     Delimiter,
+    // Only produced when the lexer is asked to keep line breaks.
+    Newline,
+    // Only produced when the lexer is asked to keep comments.
+    Comment,
     EOF,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Token {
     pub token_type: TokenType,
     pub value: String,
+    pub line: usize,
+    pub column: usize,
+    // Byte range of the token's text in the source, end exclusive.
+    pub start: usize,
+    pub end: usize,
+}
+
+impl fmt::Display for TokenType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            TokenType::Keyword => "keyword",
+            TokenType::Identifier => "identifier",
+            TokenType::Number => "number",
+            TokenType::Quantity => "quantity",
+            TokenType::String => "string",
+            TokenType::Operator => "operator",
+            TokenType::Delimiter => "delimiter",
+            TokenType::Newline => "newline",
+            TokenType::Comment => "comment",
+            TokenType::EOF => "end of input",
+        };
+        f.write_str(name)
+    }
+}
+
+// `identifier "speed"`; tokens without text show just their kind.
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.token_type {
+            TokenType::Newline | TokenType::EOF => write!(f, "{}", self.token_type),
+            _ => write!(f, "{} {:?}", self.token_type, self.value),
+        }
+    }
+}
+
+impl Token {
+    pub fn position(&self) -> Position {
+        Position {
+            line: self.line,
+            column: self.column,
+        }
+    }
+}
+
+// Source positions are carried for diagnostics only; AST equality and
+// hashing skip them, so structurally identical trees match regardless of
+// layout.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LexErrorKind {
+    // Malformed input; the message says what is wrong with it.
+    Invalid,
+    TooManyTokens { limit: usize },
+}
+
+#[derive(Debug, Clone, PartialEq, Error)]
+#[error("{message} at {line}:{column}")]
+pub struct LexError {
+    pub kind: LexErrorKind,
+    pub message: String,
+    pub position: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct LexerConfig {
+    // Extra operator spellings such as `~>`, lexed as `TokenType::Operator`.
+    pub operators: Vec<String>,
+    pub emit_newlines: bool,
+    // Emit `//` and `/* */` comments as `TokenType::Comment` tokens, for
+    // tools such as a formatter that must not drop them.
+    pub emit_comments: bool,
+    // Lexing fails once more tokens than this have been produced, not
+    // counting EOF.
+    pub max_tokens: usize,
+}
+
+impl Default for LexerConfig {
+    fn default() -> Self {
+        LexerConfig {
+            operators: Vec::new(),
+            emit_newlines: false,
+            emit_comments: false,
+            max_tokens: DEFAULT_MAX_TOKENS,
+        }
+    }
 }
 
 pub struct Lexer<'a> {
     input: &'a str,
     position: usize,
+    line: usize,
+    column: usize,
+    config: LexerConfig,
+    token_count: usize,
+    // The type of the last token yielded.
+    previous: Option<TokenType>,
+    // Set once EOF or an error has been yielded, ending iteration.
+    done: bool,
+    // One entry per `${` still open, counting the `{` opened inside it so
+    // the `}` that ends the interpolation can be told apart.
+    interpolations: Vec<usize>,
+    // Set when a string segment stopped at `${`, which is lexed next.
+    interpolation_next: bool,
+    // Set after the `}` ending an interpolation, when the rest of the
+    // string is lexed next.
+    resume_string: bool,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a str) -> Self {
-        Lexer { input, position: 0 }
+        Self::with_config(input, LexerConfig::default())
+    }
+
+    pub fn with_config(input: &'a str, config: LexerConfig) -> Self {
+        Lexer {
+            input,
+            position: 0,
+            line: 1,
+            column: 1,
+            config,
+            token_count: 0,
+            previous: None,
+            done: false,
+            interpolations: Vec::new(),
+            interpolation_next: false,
+            resume_string: false,
+        }
+    }
+
+    pub fn with_newlines(mut self) -> Self {
+        self.config.emit_newlines = true;
+        self
+    }
+
+    pub fn with_comments(mut self) -> Self {
+        self.config.emit_comments = true;
+        self
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.config.max_tokens = max_tokens;
+        self
+    }
+
+    pub fn add_operator(&mut self, op: &str) {
+        if !self.config.operators.iter().any(|existing| existing == op) {
+            self.config.operators.push(op.to_string());
+        }
     }
 
     fn next_char(&self) -> Option<char> {
         self.input[self.position..].chars().next()
     }
 
+    fn peek_char(&self) -> Option<char> {
+        let mut chars = self.input[self.position..].chars();
+        chars.next();
+        chars.next()
+    }
+
     fn consume_char(&mut self) {
-        self.position += self.next_char().map(|c| c.len_utf8()).unwrap_or(0);
+        if let Some(c) = self.next_char() {
+            self.position += c.len_utf8();
+            if c == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
     }
 
-    pub fn tokenize(&mut self) -> Vec<Token> {
-        let mut tokens = Vec::new();
+    pub fn tokenize(&mut self) -> Result<Vec<Token>, LexError> {
+        self.collect()
+    }
+
+    // Lexes the next token, skipping whitespace and comments; at the end of
+    // the input this is the EOF token.
+    fn next_token(&mut self) -> Result<Token, LexError> {
+        if self.interpolation_next {
+            self.interpolation_next = false;
+            return Ok(self.tokenize_interpolation_start());
+        }
+        if self.resume_string {
+            self.resume_string = false;
+            return self.tokenize_string_segment(self.position, self.line, self.column);
+        }
         while self.position < self.input.len() {
             let c = self.next_char().unwrap();
+            if let Some(op) = self.custom_operator() {
+                return Ok(self.tokenize_custom_operator(op));
+            }
             match c {
+                '\n' if self.config.emit_newlines => {
+                    let (start, line, column) = (self.position, self.line, self.column);
+                    self.consume_char();
+                    return Ok(Token {
+                        token_type: TokenType::Newline,
+                        value: "\n".to_string(),
+                        line,
+                        column,
+                        start,
+                        end: self.position,
+                    });
+                }
                 ' ' | '\t' | '\n' => {
                     self.consume_char();
                 }
-                '0'..='9' => tokens.push(self.tokenize_number()),
-                'a'..='z' | 'A'..='Z' => tokens.push(self.tokenize_identifier()),
-                '+' | '-' | '*' | '/' => tokens.push(self.tokenize_operator(c)),
-                '(' | ')' | '{' | '}' | ',' | ';' => tokens.push(self.tokenize_delimiter(c)),
-                _ => panic!("Unexpected character: {}", c),
+                '0'..='9' => return self.tokenize_number(),
+                '"' => return self.tokenize_string(),
+                'r' if self.peek_char() == Some('"') => return self.tokenize_raw_string(),
+                // `_1_000` reads as a number with a leading separator rather
+                // than an identifier.
+                '_' if self.is_separated_number() => {
+                    return Err(LexError {
+                        kind: LexErrorKind::Invalid,
+                        message: "Digit separator must be between digits".to_string(),
+                        position: self.position,
+                        line: self.line,
+                        column: self.column,
+                    });
+                }
+                c if c.is_alphabetic() || c == '_' => return Ok(self.tokenize_identifier()),
+                '/' if self.peek_char() == Some('/') || self.peek_char() == Some('*') => {
+                    let (start, line, column) = (self.position, self.line, self.column);
+                    if self.peek_char() == Some('/') {
+                        self.skip_line_comment();
+                    } else {
+                        self.skip_block_comment()?;
+                    }
+                    if self.config.emit_comments {
+                        return Ok(Token {
+                            token_type: TokenType::Comment,
+                            value: self.input[start..self.position].to_string(),
+                            line,
+                            column,
+                            start,
+                            end: self.position,
+                        });
+                    }
+                }
+                '+' | '-' | '*' | '/' | '%' | '<' | '>' | '=' | '!' => return Ok(self.tokenize_operator(c)),
+                '&' if self.peek_char() == Some('&') => return Ok(self.tokenize_operator(c)),
+                '|' if self.peek_char() == Some('|') => return Ok(self.tokenize_operator(c)),
+                '.' if self.peek_char() == Some('.') => return Ok(self.tokenize_operator(c)),
+                '{' | '}' if !self.interpolations.is_empty() => return Ok(self.tokenize_interpolation_brace(c)),
+                '(' | ')' | '{' | '}' | '[' | ']' | ',' | ';' | '?' | ':' => return Ok(self.tokenize_delimiter(c)),
+                _ => {
+                    return Err(LexError {
+                        kind: LexErrorKind::Invalid,
+                        message: format!("Unexpected character: {}", c),
+                        position: self.position,
+                        line: self.line,
+                        column: self.column,
+                    })
+                }
             }
         }
-        tokens.push(Token { token_type: TokenType::EOF, value: String::new() });
-        tokens
+        if !self.interpolations.is_empty() {
+            return Err(LexError {
+                kind: LexErrorKind::Invalid,
+                message: "Unterminated string interpolation".to_string(),
+                position: self.position,
+                line: self.line,
+                column: self.column,
+            });
+        }
+        Ok(Token {
+            token_type: TokenType::EOF,
+            value: String::new(),
+            line: self.line,
+            column: self.column,
+            start: self.position,
+            end: self.position,
+        })
     }
 
-    fn tokenize_number(&mut self) -> Token {
-        let start = self.position;
-        while self.next_char().map(|c| c.is_digit(10)).unwrap_or(false) {
+    fn skip_line_comment(&mut self) {
+        while self.next_char().map(|c| c != '\n').unwrap_or(false) {
             self.consume_char();
         }
-        Token {
+    }
+
+    fn skip_block_comment(&mut self) -> Result<(), LexError> {
+        let (start, line, column) = (self.position, self.line, self.column);
+        self.consume_char(); // '/'
+        self.consume_char(); // '*'
+        while !(self.next_char() == Some('*') && self.peek_char() == Some('/')) {
+            if self.next_char().is_none() {
+                return Err(LexError {
+                    kind: LexErrorKind::Invalid,
+                    message: "Unterminated block comment".to_string(),
+                    position: start,
+                    line,
+                    column,
+                });
+            }
+            self.consume_char();
+        }
+        self.consume_char(); // '*'
+        self.consume_char(); // '/'
+        Ok(())
+    }
+
+    fn tokenize_number(&mut self) -> Result<Token, LexError> {
+        let (start, line, column) = (self.position, self.line, self.column);
+        let radix = match (self.next_char(), self.peek_char()) {
+            (Some('0'), Some('x')) | (Some('0'), Some('X')) => 16,
+            (Some('0'), Some('b')) | (Some('0'), Some('B')) => 2,
+            _ => 10,
+        };
+        if radix != 10 {
+            return self.tokenize_radix_number(radix, start, line, column);
+        }
+        self.consume_digits(10)?;
+        // `0..5` is a range, not the float `0.` followed by `.5`.
+        if self.next_char() == Some('.') && self.peek_char() != Some('.') {
+            self.consume_char();
+            if !self.next_char().is_some_and(|c| c.is_ascii_digit()) {
+                return Err(LexError {
+                    kind: LexErrorKind::Invalid,
+                    message: format!("Expected digits after decimal point in {}", &self.input[start..self.position]),
+                    position: self.position,
+                    line: self.line,
+                    column: self.column,
+                });
+            }
+            self.consume_digits(10)?;
+        }
+        if self.next_char() == Some('e') || self.next_char() == Some('E') {
+            self.consume_char();
+            if self.next_char() == Some('+') || self.next_char() == Some('-') {
+                self.consume_char();
+            }
+            if !self.next_char().is_some_and(|c| c.is_ascii_digit()) {
+                return Err(LexError {
+                    kind: LexErrorKind::Invalid,
+                    message: format!("Expected exponent digits in {}", &self.input[start..self.position]),
+                    position: self.position,
+                    line: self.line,
+                    column: self.column,
+                });
+            }
+            self.consume_digits(10)?;
+        }
+        if let Some(suffix) = self.unit_suffix() {
+            for _ in suffix.chars() {
+                self.consume_char();
+            }
+            return Ok(Token {
+                token_type: TokenType::Quantity,
+                value: self.input[start..self.position].replace('_', ""),
+                line,
+                column,
+                start,
+                end: self.position,
+            });
+        }
+        if !self.input[start..self.position].contains(['.', 'e', 'E']) {
+            self.consume_int_suffix();
+        }
+        Ok(Token {
             token_type: TokenType::Number,
-            value: self.input[start..self.position].to_string(),
+            value: self.input[start..self.position].replace('_', ""),
+            line,
+            column,
+            start,
+            end: self.position,
+        })
+    }
+
+    // Digits may be grouped with single underscores (`1_000`), which are
+    // stripped from the token value; one that doesn't sit between two digits
+    // is an error.
+    fn consume_digits(&mut self, radix: u32) -> Result<(), LexError> {
+        while let Some(c) = self.next_char() {
+            if c == '_' {
+                let follows_digit = self.input[..self.position].ends_with(|c: char| c.is_digit(radix));
+                let precedes_digit = self.peek_char().map(|c| c.is_digit(radix)).unwrap_or(false);
+                if !follows_digit || !precedes_digit {
+                    return Err(LexError {
+                        kind: LexErrorKind::Invalid,
+                        message: "Digit separator must be between digits".to_string(),
+                        position: self.position,
+                        line: self.line,
+                        column: self.column,
+                    });
+                }
+            } else if !c.is_digit(radix) {
+                break;
+            }
+            self.consume_char();
         }
+        Ok(())
+    }
+
+    fn is_separated_number(&self) -> bool {
+        let rest = &self.input[self.position..];
+        let word = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .map_or(rest, |end| &rest[..end]);
+        word.chars().any(|c| c.is_ascii_digit()) && word.chars().all(|c| c.is_ascii_digit() || c == '_')
+    }
+
+    fn consume_int_suffix(&mut self) -> &'static str {
+        let rest = &self.input[self.position..];
+        let suffix = INT_SUFFIXES
+            .iter()
+            .copied()
+            .find(|suffix| rest.starts_with(suffix) && !rest[suffix.len()..].starts_with(|c: char| c.is_alphanumeric()))
+            .unwrap_or("");
+        for _ in suffix.chars() {
+            self.consume_char();
+        }
+        suffix
+    }
+
+    fn unit_suffix(&self) -> Option<&'static str> {
+        let rest = &self.input[self.position..];
+        UNIT_SUFFIXES.iter().copied().find(|suffix| {
+            rest.starts_with(suffix)
+                && !rest[suffix.len()..]
+                    .chars()
+                    .next()
+                    .map(|c| c.is_alphanumeric() || c == '_')
+                    .unwrap_or(false)
+        })
+    }
+
+    // Hex and binary literals are normalized to decimal so the parser only
+    // ever sees base-10 number tokens.
+    fn tokenize_radix_number(&mut self, radix: u32, start: usize, line: usize, column: usize) -> Result<Token, LexError> {
+        self.consume_char(); // '0'
+        self.consume_char(); // 'x' or 'b'
+        let digits_start = self.position;
+        self.consume_digits(radix)?;
+        let digits = self.input[digits_start..self.position].replace('_', "");
+        if digits.is_empty() {
+            return Err(LexError {
+                kind: LexErrorKind::Invalid,
+                message: format!("Expected digits after {}", &self.input[start..digits_start]),
+                position: self.position,
+                line: self.line,
+                column: self.column,
+            });
+        }
+        match i64::from_str_radix(&digits, radix) {
+            Ok(value) => Ok(Token {
+                token_type: TokenType::Number,
+                value: format!("{}{}", value, self.consume_int_suffix()),
+                line,
+                column,
+                start,
+                end: self.position,
+            }),
+            Err(_) => Err(LexError {
+                kind: LexErrorKind::Invalid,
+                message: format!("Integer literal out of range: {}", &self.input[start..self.position]),
+                position: start,
+                line,
+                column,
+            }),
+        }
+    }
+
+    // Escapes are decoded into the token value; the span still covers the
+    // literal as written. A string containing `${...}` is lexed as a string
+    // token for the text before it, the delimiter `${`, the tokens of the
+    // expression, `}`, and another string token for the text after it; that
+    // last one is emitted even when empty.
+    fn tokenize_string(&mut self) -> Result<Token, LexError> {
+        let (start, line, column) = (self.position, self.line, self.column);
+        self.consume_char(); // opening '"'
+        self.tokenize_string_segment(start, line, column)
+    }
+
+    fn tokenize_string_segment(&mut self, start: usize, line: usize, column: usize) -> Result<Token, LexError> {
+        let mut value = String::new();
+        loop {
+            match self.next_char() {
+                None => {
+                    return Err(LexError {
+                        kind: LexErrorKind::Invalid,
+                        message: "Unterminated string literal".to_string(),
+                        position: start,
+                        line,
+                        column,
+                    })
+                }
+                Some('"') => break,
+                Some('$') if self.peek_char() == Some('{') => {
+                    self.interpolation_next = true;
+                    return Ok(Token {
+                        token_type: TokenType::String,
+                        value,
+                        line,
+                        column,
+                        start,
+                        end: self.position,
+                    })
+                }
+                Some('\\') => {
+                    let (escape_start, escape_line, escape_column) = (self.position, self.line, self.column);
+                    self.consume_char(); // '\\'
+                    let decoded = match self.next_char() {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('$') => '$',
+                        other => {
+                            return Err(LexError {
+                                kind: LexErrorKind::Invalid,
+                                message: format!("Invalid escape sequence \\{}", other.map(String::from).unwrap_or_default()),
+                                position: escape_start,
+                                line: escape_line,
+                                column: escape_column,
+                            })
+                        }
+                    };
+                    self.consume_char();
+                    value.push(decoded);
+                }
+                Some(c) => {
+                    self.consume_char();
+                    value.push(c);
+                }
+            }
+        }
+        self.consume_char(); // closing '"'
+        Ok(Token {
+            token_type: TokenType::String,
+            value,
+            line,
+            column,
+            start,
+            end: self.position,
+        })
+    }
+
+    // `r"..."` keeps its contents verbatim, backslashes and line breaks
+    // included.
+    fn tokenize_raw_string(&mut self) -> Result<Token, LexError> {
+        let (start, line, column) = (self.position, self.line, self.column);
+        self.consume_char(); // 'r'
+        self.consume_char(); // opening '"'
+        while self.next_char().map(|c| c != '"').unwrap_or(false) {
+            self.consume_char();
+        }
+        if self.next_char().is_none() {
+            return Err(LexError {
+                kind: LexErrorKind::Invalid,
+                message: "Unterminated string literal".to_string(),
+                position: start,
+                line,
+                column,
+            });
+        }
+        let value = self.input[start + 2..self.position].to_string();
+        self.consume_char(); // closing '"'
+        Ok(Token {
+            token_type: TokenType::String,
+            value,
+            line,
+            column,
+            start,
+            end: self.position,
+        })
     }
 
     fn tokenize_identifier(&mut self) -> Token {
-        let start = self.position;
-        while self.next_char().map(|c| c.is_alphanumeric()).unwrap_or(false) {
+        let (start, line, column) = (self.position, self.line, self.column);
+        while self.next_char().map(|c| c.is_alphanumeric() || c == '_').unwrap_or(false) {
             self.consume_char();
         }
         let value = self.input[start..self.position].to_string();
-        if value == "function" || value == "if" {
-            Token {
-                token_type: TokenType::Keyword,
-                value,
-            }
+        let token_type = if KEYWORDS.contains(&value.as_str()) {
+            TokenType::Keyword
         } else {
-            Token {
-                token_type: TokenType::Identifier,
-                value,
-            }
+            TokenType::Identifier
+        };
+        Token {
+            token_type,
+            value,
+            line,
+            column,
+            start,
+            end: self.position,
         }
     }
 
     fn tokenize_operator(&mut self, c: char) -> Token {
+        let (start, line, column) = (self.position, self.line, self.column);
         self.consume_char();
+        let mut value = c.to_string();
+        if (c == '*' || matches!(c, '+' | '-') && self.at_postfix_step()) && self.next_char() == Some(c) {
+            self.consume_char();
+            value.push(c);
+        } else if c == '<' && self.next_char() == Some('>') {
+            // The legacy spelling of `!=`, normalized so nothing downstream
+            // needs to know about it.
+            self.consume_char();
+            value = "!=".to_string();
+        } else if matches!(c, '<' | '>' | '=' | '!' | '+' | '-' | '*' | '/') && self.next_char() == Some('=') {
+            self.consume_char();
+            value.push('=');
+        } else if matches!(c, '&' | '|' | '.') {
+            self.consume_char();
+            value.push(c);
+        }
         Token {
             token_type: TokenType::Operator,
-            value: c.to_string(),
+            value,
+            line,
+            column,
+            start,
+            end: self.position,
+        }
+    }
+
+    // `++` and `--` are only lexed as one token straight after a name and
+    // before the end of the statement, the one place the postfix statement
+    // can appear; elsewhere `a--b` is still `a - -b`. Called with the first
+    // `+` or `-` consumed.
+    fn at_postfix_step(&self) -> bool {
+        let rest = self.input[self.position..].get(1..).unwrap_or("");
+        self.previous == Some(TokenType::Identifier)
+            && rest
+                .trim_start_matches([' ', '\t'])
+                .chars()
+                .next()
+                .is_none_or(|c| matches!(c, ';' | ')' | '}' | '\n' | '\r'))
+    }
+
+    // The longest configured operator at the current position, if it is
+    // longer than any built-in operator or comment opener starting here.
+    fn custom_operator(&self) -> Option<String> {
+        let rest = &self.input[self.position..];
+        let op = self
+            .config
+            .operators
+            .iter()
+            .filter(|op| !op.is_empty() && rest.starts_with(op.as_str()))
+            .max_by_key(|op| op.len())?;
+        if op.len() > self.builtin_operator_len() {
+            Some(op.clone())
+        } else {
+            None
+        }
+    }
+
+    fn builtin_operator_len(&self) -> usize {
+        match (self.next_char(), self.peek_char()) {
+            (Some('<' | '>' | '=' | '!' | '+' | '-' | '*' | '/'), Some('=')) => 2,
+            (Some('/'), Some('/' | '*')) | (Some('*'), Some('*')) | (Some('&'), Some('&')) | (Some('|'), Some('|')) => 2,
+            (Some('.'), Some('.')) | (Some('<'), Some('>')) => 2,
+            (Some('+' | '-' | '*' | '/' | '%' | '<' | '>' | '=' | '!'), _) => 1,
+            _ => 0,
+        }
+    }
+
+    fn tokenize_custom_operator(&mut self, op: String) -> Token {
+        let (start, line, column) = (self.position, self.line, self.column);
+        for _ in op.chars() {
+            self.consume_char();
+        }
+        Token {
+            token_type: TokenType::Operator,
+            value: op,
+            line,
+            column,
+            start,
+            end: self.position,
+        }
+    }
+
+    fn tokenize_interpolation_start(&mut self) -> Token {
+        let (start, line, column) = (self.position, self.line, self.column);
+        self.consume_char(); // '$'
+        self.consume_char(); // '{'
+        self.interpolations.push(0);
+        Token {
+            token_type: TokenType::Delimiter,
+            value: "${".to_string(),
+            line,
+            column,
+            start,
+            end: self.position,
         }
     }
 
+    fn tokenize_interpolation_brace(&mut self, c: char) -> Token {
+        let depth = self.interpolations.last_mut().unwrap();
+        match c {
+            '{' => *depth += 1,
+            _ if *depth > 0 => *depth -= 1,
+            _ => {
+                self.interpolations.pop();
+                self.resume_string = true;
+            }
+        }
+        self.tokenize_delimiter(c)
+    }
+
     fn tokenize_delimiter(&mut self, c: char) -> Token {
+        let (start, line, column) = (self.position, self.line, self.column);
         self.consume_char();
         Token {
             token_type: TokenType::Delimiter,
             value: c.to_string(),
+            line,
+            column,
+            start,
+            end: self.position,
+        }
+    }
+}
+
+// Yields tokens on demand, ending after the EOF token or the first error.
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Result<Token, LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
         }
+        let mut token = self.next_token();
+        if let Ok(next) = &token {
+            self.previous = Some(next.token_type.clone());
+            if next.token_type != TokenType::EOF {
+                self.token_count += 1;
+                if self.token_count > self.config.max_tokens {
+                    token = Err(LexError {
+                        kind: LexErrorKind::TooManyTokens { limit: self.config.max_tokens },
+                        message: format!("Too many tokens, the limit is {}", self.config.max_tokens),
+                        position: next.start,
+                        line: next.line,
+                        column: next.column,
+                    });
+                }
+            }
+        }
+        self.done = match &token {
+            Ok(token) => token.token_type == TokenType::EOF,
+            Err(_) => true,
+        };
+        Some(token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lex(source: &str) -> Result<Vec<Token>, LexError> {
+        Lexer::new(source).tokenize()
+    }
+
+    fn tokens(source: &str) -> Vec<(TokenType, String)> {
+        lex(source).unwrap().into_iter().map(|token| (token.token_type, token.value)).collect()
+    }
+
+    fn number(source: &str) -> String {
+        let tokens = lex(source).unwrap();
+        assert_eq!((tokens.len(), &tokens[0].token_type), (2, &TokenType::Number), "{}", source);
+        tokens[0].value.clone()
+    }
+
+    #[test]
+    fn floats_are_one_number_token() {
+        assert_eq!(number("2.5"), "2.5");
+        assert_eq!(number("0.75"), "0.75");
+    }
+
+    #[test]
+    fn float_without_fraction_digits_is_an_error() {
+        let err = lex("x = 3.;").unwrap_err();
+        assert_eq!(err.kind, LexErrorKind::Invalid);
+        assert!(err.message.contains("decimal point"), "{}", err.message);
+    }
+
+    #[test]
+    fn scientific_notation_is_a_float() {
+        assert_eq!(number("1.5e3"), "1.5e3");
+        assert_eq!(number("2E-2"), "2E-2");
+        assert_eq!(number("4e+1"), "4e+1");
+        assert_eq!(crate::parse_str("x = 2E-2;").unwrap(), crate::parse_str("x = 0.02;").unwrap());
+    }
+
+    #[test]
+    fn exponent_without_digits_is_an_error() {
+        let err = lex("x = 3e;").unwrap_err();
+        assert_eq!(err.kind, LexErrorKind::Invalid);
+        assert!(err.message.contains("exponent"), "{}", err.message);
+        assert!(lex("x = 3e-;").is_err());
+    }
+
+    #[test]
+    fn two_character_comparisons_are_one_operator() {
+        assert_eq!(tokens("speed >= 60")[1], (TokenType::Operator, ">=".to_string()));
+        assert_eq!(tokens("x != y")[1], (TokenType::Operator, "!=".to_string()));
+        assert_eq!(tokens("x==y").len(), 4);
+        assert_eq!(tokens("x = y")[1], (TokenType::Operator, "=".to_string()));
+    }
+
+    #[test]
+    fn strings_hold_their_content_without_quotes() {
+        assert_eq!(tokens("\"sport\"")[0], (TokenType::String, "sport".to_string()));
+        let err = lex("x = \"abc").unwrap_err();
+        assert_eq!((err.message.as_str(), err.position), ("Unterminated string literal", 4));
+    }
+
+    #[test]
+    fn line_comments_produce_no_tokens() {
+        assert_eq!(tokens("// set cruise speed\nspeed = 10 / 2;"), tokens("speed = 10 / 2;"));
+        assert_eq!(tokens("speed = 1; // trailing"), tokens("speed = 1;"));
+        assert_eq!(tokens("10 / 2")[1], (TokenType::Operator, "/".to_string()));
+    }
+
+    #[test]
+    fn block_comments_span_lines() {
+        assert_eq!(tokens("a = 1;\n/* x = 2;\n y = 3; */\nb = 2;"), tokens("a = 1; b = 2;"));
+        assert_eq!(tokens("a = 1;/**/"), tokens("a = 1;"));
+    }
+
+    #[test]
+    fn unterminated_block_comment_is_an_error() {
+        let err = lex("a = 1; /* never closed *").unwrap_err();
+        assert_eq!((err.message.as_str(), err.position), ("Unterminated block comment", 7));
+    }
+
+    #[test]
+    fn unexpected_character_reports_its_offset() {
+        let err = lex("speed = 1 @ 2;").unwrap_err();
+        assert_eq!((err.message.as_str(), err.position), ("Unexpected character: @", 10));
+    }
+
+    #[test]
+    fn tokens_carry_line_and_column() {
+        let source = "
+        function startEngine() {
+            speed = 100;
+            if (speed > 60) {
+                applyBrakes();
+            }
+        }
+    ";
+        let tokens = lex(source).unwrap();
+        let call = tokens.iter().find(|token| token.value == "applyBrakes").unwrap();
+        assert_eq!((call.line, call.column), (5, 17));
+        let err = lex("a\n  @").unwrap_err();
+        assert_eq!((err.line, err.column), (2, 3));
+    }
+
+    #[test]
+    fn hex_and_binary_literals_are_normalized() {
+        assert_eq!(number("0xFF"), "255");
+        assert_eq!(number("0b1010"), "10");
+        assert_eq!(number("0"), "0");
+        assert_eq!(lex("x = 0x;").unwrap_err().message, "Expected digits after 0x");
+    }
+
+    #[test]
+    fn identifiers_take_underscores_and_digits() {
+        for name in ["max_speed", "sensor1", "_private", "sensor_1"] {
+            assert_eq!(tokens(name), vec![(TokenType::Identifier, name.to_string()), (TokenType::EOF, String::new())]);
+        }
+        assert_eq!(tokens("1abc")[0], (TokenType::Number, "1".to_string()));
+    }
+
+    #[test]
+    fn unit_suffix_makes_a_quantity() {
+        assert_eq!(tokens("100km/h")[0], (TokenType::Quantity, "100km/h".to_string()));
+        assert_eq!(tokens("3mph 2.5bar 4s 5m")[1], (TokenType::Quantity, "2.5bar".to_string()));
+        assert_eq!(tokens("x / 2").len(), 4);
+    }
+
+    #[test]
+    fn identifiers_may_be_non_ascii() {
+        let names: Vec<_> = tokens("geschwindigkeit = 速度 + größe2;")
+            .into_iter()
+            .filter(|(token_type, _)| *token_type == TokenType::Identifier)
+            .map(|(_, value)| value)
+            .collect();
+        assert_eq!(names, vec!["geschwindigkeit", "速度", "größe2"]);
+        assert_eq!(lex("速度 = 1;").unwrap()[1].column, 4);
+    }
+
+    #[test]
+    fn every_keyword_lexes_as_a_keyword() {
+        for keyword in ["function", "if", "else", "while", "return", "let", "const", "on", "true", "false"] {
+            assert_eq!(tokens(keyword)[0], (TokenType::Keyword, keyword.to_string()));
+        }
+        assert_eq!(tokens("iffy")[0].0, TokenType::Identifier);
+    }
+
+    #[test]
+    fn tokens_span_their_source_bytes() {
+        let source = "speed = 100;";
+        let tokens = lex(source).unwrap();
+        assert_eq!((tokens[0].start, tokens[0].end), (0, 5));
+        assert_eq!(&source[tokens[0].start..tokens[0].end], "speed");
+        let source = "x = 0xFF + \"hi\" + 2km/h <= 1;";
+        let spans: Vec<_> = lex(source).unwrap().iter().map(|token| &source[token.start..token.end]).collect();
+        assert_eq!(spans, vec!["x", "=", "0xFF", "+", "\"hi\"", "+", "2km/h", "<=", "1", ";", ""]);
+    }
+
+    #[test]
+    fn newlines_are_only_emitted_on_request() {
+        assert_eq!(tokens("a\nb").len(), 3);
+        let tokens = Lexer::new("a\nb").with_newlines().tokenize().unwrap();
+        assert_eq!(tokens[1].token_type, TokenType::Newline);
+    }
+
+    #[test]
+    fn custom_operators_take_the_longest_match() {
+        let config = LexerConfig {
+            operators: vec!["~".into(), "~>".into()],
+            ..LexerConfig::default()
+        };
+        let tokens = Lexer::with_config("a ~> b ~ c", config).tokenize().unwrap();
+        let values: Vec<_> = tokens.iter().map(|token| token.value.as_str()).collect();
+        assert_eq!(values, vec!["a", "~>", "b", "~", "c", ""]);
+        assert!(lex("a ~> b").is_err());
+    }
+
+    #[test]
+    fn escapes_are_decoded() {
+        assert_eq!(lex(r#""a\nb\t\"q\"\\""#).unwrap()[0].value, "a\nb\t\"q\"\\");
+        let err = lex("x = \"ok\\q\";").unwrap_err();
+        assert_eq!((err.message.as_str(), err.position, err.column), ("Invalid escape sequence \\q", 7, 8));
+    }
+
+    #[test]
+    fn raw_strings_span_lines_undecoded() {
+        assert_eq!(tokens("r\"line1\\n\nline2\" rx")[..2], [(TokenType::String, "line1\\n\nline2".to_string()), (TokenType::Identifier, "rx".to_string())]);
+        assert!(lex("r\"abc").is_err());
+    }
+
+    #[test]
+    fn tokens_display_concisely() {
+        let tokens = lex("speed 100").unwrap();
+        assert_eq!(tokens[0].to_string(), "identifier \"speed\"");
+        assert_eq!(tokens[1].to_string(), "number \"100\"");
+        assert_eq!(tokens[2].to_string(), "end of input");
+        assert_eq!(TokenType::Keyword.to_string(), "keyword");
+    }
+
+    #[test]
+    fn iterating_matches_tokenize() {
+        let source = "function f() { x = 1.5km; } // done\n r\"raw\"";
+        let lazy: Vec<Token> = Lexer::new(source).collect::<Result<_, _>>().unwrap();
+        assert_eq!(lazy, lex(source).unwrap());
+        assert_eq!(lazy.last().unwrap().token_type, TokenType::EOF);
+    }
+
+    #[test]
+    fn iteration_stops_after_eof_or_an_error() {
+        let mut lexer = Lexer::new("a @ b");
+        assert!(lexer.next().unwrap().is_ok());
+        assert!(lexer.next().unwrap().is_err());
+        assert!(lexer.next().is_none());
+        let mut lexer = Lexer::new("");
+        assert_eq!(lexer.next().unwrap().unwrap().token_type, TokenType::EOF);
+        assert!(lexer.next().is_none());
+    }
+
+    #[test]
+    fn comments_are_only_emitted_on_request() {
+        let source = "// note\nx = 1;";
+        assert!(tokens(source).iter().all(|(token_type, _)| *token_type != TokenType::Comment));
+        let tokens = Lexer::new(source).with_comments().tokenize().unwrap();
+        assert_eq!((&tokens[0].token_type, tokens[0].value.as_str(), tokens[0].start, tokens[0].end), (&TokenType::Comment, "// note", 0, 7));
+    }
+
+    #[test]
+    fn angle_brackets_lex_as_not_equal() {
+        let tokens = lex("a<>b").unwrap();
+        assert_eq!((tokens[1].value.as_str(), tokens[1].start, tokens[1].end), ("!=", 1, 3));
+        let values: Vec<_> = lex("a < b > c <= d").unwrap().into_iter().map(|token| token.value).collect();
+        assert_eq!(values, ["a", "<", "b", ">", "c", "<=", "d", ""]);
+    }
+
+    #[test]
+    fn underscores_separate_digits() {
+        assert_eq!(number("1_000"), "1000");
+        assert_eq!(number("0xff_ff"), "65535");
+        assert_eq!(number("1_0.2_5"), "10.25");
+    }
+
+    #[test]
+    fn misplaced_underscores_are_errors() {
+        for source in ["x = 1__0;", "x = _1;", "x = 1_;", "x = 0x_f;", "x = 1._5;"] {
+            assert!(lex(source).is_err(), "{}", source);
+        }
+        assert_eq!(tokens("_x1")[0], (TokenType::Identifier, "_x1".to_string()));
+    }
+
+    #[test]
+    fn token_limit_is_enforced() {
+        let err = Lexer::new("x = 1; y = 2;").with_max_tokens(5).tokenize().unwrap_err();
+        assert_eq!(err.kind, LexErrorKind::TooManyTokens { limit: 5 });
+        assert_eq!((err.message.as_str(), err.position), ("Too many tokens, the limit is 5", 9));
+        assert_eq!(Lexer::new("x = 1;").with_max_tokens(4).tokenize().unwrap().len(), 5);
+        assert_eq!(LexerConfig::default().max_tokens, 1_000_000);
     }
 }
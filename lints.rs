@@ -0,0 +1,118 @@
+use crate::lexer::Position;
+use crate::parser::{Expr, Stmt};
+use crate::visit::{walk_stmt, walk_stmts, Visitor};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Lint {
+    // The first statement of a block that follows a `return`, `break` or
+    // `continue` at the same level.
+    UnreachableCode(Stmt),
+    // `if (x = 5)` and the like, where `==` was probably meant.
+    AssignmentInCondition(String, Position),
+}
+
+pub fn lint(stmts: &[Stmt]) -> Vec<Lint> {
+    let mut linter = Linter { lints: Vec::new() };
+    linter.check_block(stmts);
+    walk_stmts(&mut linter, stmts);
+    linter.lints
+}
+
+struct Linter {
+    lints: Vec<Lint>,
+}
+
+impl Linter {
+    fn check_condition(&mut self, condition: &Expr) {
+        if let Expr::Assign(name, _, position) = condition {
+            self.lints.push(Lint::AssignmentInCondition(name.clone(), *position));
+        }
+    }
+
+    fn check_block(&mut self, stmts: &[Stmt]) {
+        let exit = stmts
+            .iter()
+            .position(|stmt| matches!(stmt, Stmt::Return(_) | Stmt::Break | Stmt::Continue));
+        if let Some(unreachable) = exit.and_then(|exit| stmts.get(exit + 1)) {
+            self.lints.push(Lint::UnreachableCode(unreachable.clone()));
+        }
+    }
+}
+
+impl Visitor for Linter {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::FunctionDeclaration(_, _, body, _)
+            | Stmt::EventHandler(_, body)
+            | Stmt::ForRange(_, _, body)
+            | Stmt::Block(body) => self.check_block(body),
+            Stmt::While(condition, body) => {
+                self.check_condition(condition);
+                self.check_block(body);
+            }
+            Stmt::ForClassic(_, condition, _, body) => {
+                self.check_condition(condition);
+                self.check_block(body);
+            }
+            Stmt::If(condition, body, else_body) => {
+                self.check_condition(condition);
+                self.check_block(body);
+                if let Some(else_body) = else_body {
+                    self.check_block(else_body);
+                }
+            }
+            Stmt::IfChain(arms, else_body) => {
+                for (condition, body) in arms {
+                    self.check_condition(condition);
+                    self.check_block(body);
+                }
+                if let Some(else_body) = else_body {
+                    self.check_block(else_body);
+                }
+            }
+            Stmt::Switch(_, arms, else_body) => {
+                for (_, body) in arms {
+                    self.check_block(body);
+                }
+                if let Some(else_body) = else_body {
+                    self.check_block(else_body);
+                }
+            }
+            _ => {}
+        }
+        walk_stmt(self, stmt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(source: &str) -> Vec<Lint> {
+        lint(&crate::parse_str(source).unwrap())
+    }
+
+    #[test]
+    fn code_after_return_is_unreachable() {
+        let after = crate::parse_str("x = 2;").unwrap().remove(0);
+        assert_eq!(check("function f() { return 1; x = 2; y = 3; }"), vec![Lint::UnreachableCode(after)]);
+        assert_eq!(check("while (a) { if (b) { break; f(); } }").len(), 1);
+    }
+
+    #[test]
+    fn sibling_branches_are_reachable() {
+        assert!(check("function f() { if (a) { return 1; } else { x = 2; } y = 3; }").is_empty());
+    }
+
+    #[test]
+    fn assignment_as_condition_warns() {
+        assert_eq!(check("if (x = 5) { f(); }"), vec![Lint::AssignmentInCondition("x".into(), Position { line: 1, column: 5 })]);
+        assert_eq!(check("while (y = g()) { }\nif (a) { } else if (b = 1) { }").len(), 2);
+    }
+
+    #[test]
+    fn comparison_as_condition_does_not_warn() {
+        assert!(check("if (x == 5) { f(); }").is_empty());
+        assert!(check("while ((s = readSpeed()) > 0) { f(); }").is_empty());
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::lexer::Lexer;
+use crate::parser::{ParseError, Parser, Stmt};
+
+#[derive(Debug, Error)]
+pub enum LoadError {
+    #[error("cannot read {}: {1}", .0.display())]
+    Io(PathBuf, io::Error),
+    #[error("{}: {1}", .0.display())]
+    Parse(PathBuf, ParseError),
+    // The chain of imports from the file that started the cycle back to it.
+    #[error("circular import: {}", display_chain(.0))]
+    CircularImport(Vec<PathBuf>),
+}
+
+// Reads and parses the program at `path`, replacing each top-level
+// `import "file";` with the functions, variables, constants and handlers
+// declared at the top level of that file. Imported declarations come before
+// the importer's own statements, and paths are relative to the importing
+// file. A file imported more than once is only inlined the first time.
+pub fn load(path: impl AsRef<Path>) -> Result<Vec<Stmt>, LoadError> {
+    let mut loader = Loader {
+        stack: Vec::new(),
+        loaded: HashSet::new(),
+    };
+    loader.load_file(path.as_ref())
+}
+
+struct Loader {
+    // The files currently being loaded, outermost first.
+    stack: Vec<PathBuf>,
+    loaded: HashSet<PathBuf>,
+}
+
+impl Loader {
+    fn load_file(&mut self, path: &Path) -> Result<Vec<Stmt>, LoadError> {
+        let path = fs::canonicalize(path).map_err(|err| LoadError::Io(path.to_path_buf(), err))?;
+        if let Some(start) = self.stack.iter().position(|loading| *loading == path) {
+            let mut chain = self.stack[start..].to_vec();
+            chain.push(path);
+            return Err(LoadError::CircularImport(chain));
+        }
+        if self.loaded.contains(&path) {
+            return Ok(Vec::new());
+        }
+        let source = fs::read_to_string(&path).map_err(|err| LoadError::Io(path.clone(), err))?;
+        let stmts = Parser::try_new(Lexer::new(&source))
+            .map_err(ParseError::from)
+            .and_then(|mut parser| parser.parse())
+            .map_err(|err| LoadError::Parse(path.clone(), err))?;
+        let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        self.stack.push(path.clone());
+        let mut program = Vec::new();
+        let mut own = Vec::new();
+        for stmt in stmts {
+            match stmt {
+                Stmt::Import(import) => {
+                    let imported = self.load_file(&dir.join(import))?;
+                    program.extend(imported.into_iter().filter(is_declaration));
+                }
+                stmt => own.push(stmt),
+            }
+        }
+        self.stack.pop();
+        self.loaded.insert(path);
+        program.extend(own);
+        Ok(program)
+    }
+}
+
+fn is_declaration(stmt: &Stmt) -> bool {
+    matches!(
+        stmt,
+        Stmt::FunctionDeclaration(..) | Stmt::VarDecl(..) | Stmt::ConstDecl(..) | Stmt::EventHandler(..)
+    )
+}
+
+fn display_chain(chain: &[PathBuf]) -> String {
+    let names: Vec<String> = chain.iter().map(|path| path.display().to_string()).collect();
+    names.join(" -> ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::{Interpreter, Value};
+
+    // A fresh directory holding `files`, removed again by the caller.
+    fn write_files(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("vac_loader_{}_{}", test, std::process::id()));
+        for (name, source) in files {
+            let path = dir.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, source).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn imported_function_is_callable() {
+        let dir = write_files(
+            "import",
+            &[
+                ("lib/common.vac", "import \"../util.vac\";\nfunction double(x) { return helper(x) * 2; }\nlog(\"not inlined\");\n"),
+                ("util.vac", "function helper(x) { return x + 1; }\n"),
+                ("main.vac", "import \"lib/common.vac\";\nimport \"util.vac\";\nr = double(4);\n"),
+            ],
+        );
+        let program = load(dir.join("main.vac")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(program.len(), 3, "{:?}", program);
+        let mut interpreter = Interpreter::new();
+        interpreter.run(&program).unwrap();
+        assert_eq!(interpreter.get("r"), Some(&Value::Int(10)));
+    }
+
+    #[test]
+    fn circular_import_is_an_error() {
+        let dir = write_files("cycle", &[("a.vac", "import \"b.vac\";\n"), ("b.vac", "import \"a.vac\";\n")]);
+        let result = load(dir.join("a.vac"));
+        fs::remove_dir_all(&dir).unwrap();
+        match result {
+            Err(LoadError::CircularImport(chain)) => assert_eq!(chain.len(), 3),
+            other => panic!("expected a circular import: {:?}", other),
+        }
+    }
+}
@@ -1,11 +1,101 @@
+mod arity;
+mod builtins;
+mod compiler;
+mod const_eval;
+mod diagnostic;
+mod error;
+mod fold;
+mod hash;
+mod interpreter;
 mod lexer;
+mod lints;
+mod loader;
+mod namespace;
 mod parser;
+mod pipeline;
+mod printer;
+mod purity;
+mod rename;
+mod resolver;
+#[cfg(feature = "schema")]
+mod schema;
+mod typeck;
+mod visit;
+mod viz;
+mod vm;
 
+use std::fs;
+use std::process;
+
+use diagnostic::Diagnostic;
+use error::VacError;
 use lexer::{Lexer, Token};
-use parser::Parser;
+use parser::{Parser, Stmt};
+
+pub fn tokenize_str(src: &str) -> Result<Vec<Token>, VacError> {
+    Ok(Lexer::new(src).tokenize()?)
+}
+
+pub fn parse_str(src: &str) -> Result<Vec<Stmt>, VacError> {
+    Ok(Parser::try_new(Lexer::new(src))?.parse()?)
+}
 
+// Prints the program back as formatted source; `--ast` dumps the parsed
+// tree instead and `--tokens` the token stream.
 fn main() {
-    let code = "
+    let mut dump_tokens = false;
+    let mut dump_ast = false;
+    let mut path = None;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--tokens" => dump_tokens = true,
+            "--ast" => dump_ast = true,
+            _ => path = Some(arg),
+        }
+    }
+    let path = match path {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: vac_parser [--tokens | --ast] <file.vac>");
+            process::exit(2);
+        }
+    };
+    let code = match fs::read_to_string(&path) {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("error: cannot read {}: {}", path, err);
+            process::exit(1);
+        }
+    };
+
+    let result = if dump_tokens {
+        tokenize_str(&code).map(|tokens| {
+            for token in tokens {
+                println!("{}:{} {}", token.line, token.column, token);
+            }
+        })
+    } else if dump_ast {
+        parse_str(&code).map(|ast| println!("{:#?}", ast))
+    } else {
+        parse_str(&code).map(|ast| print!("{}", printer::pretty_print(&ast)))
+    };
+    if let Err(err) = result {
+        match err {
+            VacError::Lex(err) => eprint!("{}", Diagnostic::from(&err).render(&code)),
+            VacError::Parse(err) => eprint!("{}", Diagnostic::from(&err).render(&code)),
+            err => eprintln!("{}", err),
+        }
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_str_returns_the_readme_function() {
+        let source = "
         function startEngine() {
             speed = 100;
             if (speed > 60) {
@@ -13,10 +103,13 @@ fn main() {
             }
         }
     ";
+        let ast = parse_str(source).unwrap();
+        assert!(matches!(&ast[..], [Stmt::FunctionDeclaration(name, params, body, false)] if name == "startEngine" && params.is_empty() && body.len() == 2));
+    }
 
-    let lexer = Lexer::new(code);
-    let mut parser = Parser::new(lexer);
-    let ast = parser.parse();
-
-    println!("{:#?}", ast);
+    #[test]
+    fn tokenize_str_reports_lex_errors() {
+        assert_eq!(tokenize_str("speed = 1;").unwrap().len(), 5);
+        assert!(matches!(tokenize_str("a @"), Err(VacError::Lex(err)) if err.column == 3));
+    }
 }
@@ -0,0 +1,192 @@
+use crate::builtins::BUILTINS;
+use crate::parser::{Expr, Stmt, StringPart};
+
+// Prefixes the names a module defines so several modules can be lowered
+// into one program: functions, variables, constants and parameters become
+// `{module}__{name}`. Builtins keep their names, as do any natives the host
+// registers and declares with `with_external`; event names are left alone
+// since they come from outside.
+#[derive(Debug, Clone)]
+pub struct Namespace {
+    module: String,
+    externals: Vec<String>,
+}
+
+impl Namespace {
+    pub fn new(module: &str) -> Self {
+        Namespace {
+            module: module.to_string(),
+            externals: BUILTINS.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+
+    pub fn with_external(mut self, name: &str) -> Self {
+        self.externals.push(name.to_string());
+        self
+    }
+
+    pub fn mangle(&self, name: &str) -> String {
+        format!("{}__{}", self.module, name)
+    }
+
+    pub fn apply(&self, stmts: &mut [Stmt]) {
+        for stmt in stmts {
+            self.stmt(stmt);
+        }
+    }
+
+    fn name(&self, name: &mut String) {
+        *name = self.mangle(name);
+    }
+
+    fn stmt(&self, stmt: &mut Stmt) {
+        match stmt {
+            Stmt::VarDecl(name, value) => {
+                self.name(name);
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+            }
+            Stmt::ConstDecl(name, value) | Stmt::Assignment(name, value) | Stmt::CompoundAssignment(name, _, value) => {
+                self.name(name);
+                self.expr(value);
+            }
+            Stmt::Increment(name, _) => self.name(name),
+            Stmt::FunctionDeclaration(name, params, body, _) => {
+                self.name(name);
+                for (param, default) in params.iter_mut() {
+                    self.name(param);
+                    if let Some(default) = default {
+                        self.expr(default);
+                    }
+                }
+                self.apply(body);
+            }
+            Stmt::EventHandler(_, body) | Stmt::Block(body) => self.apply(body),
+            Stmt::If(condition, body, else_body) => {
+                self.expr(condition);
+                self.apply(body);
+                if let Some(else_body) = else_body {
+                    self.apply(else_body);
+                }
+            }
+            Stmt::IfChain(arms, else_body) => self.arms(arms, else_body),
+            Stmt::Switch(subject, cases, default) => {
+                self.expr(subject);
+                self.arms(cases, default);
+            }
+            Stmt::While(condition, body) => {
+                self.expr(condition);
+                self.apply(body);
+            }
+            Stmt::ForRange(name, range, body) => {
+                self.name(name);
+                self.expr(range);
+                self.apply(body);
+            }
+            Stmt::ForClassic(init, condition, step, body) => {
+                self.stmt(init);
+                self.expr(condition);
+                self.stmt(step);
+                self.apply(body);
+            }
+            Stmt::Return(value) => {
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+            }
+            Stmt::Expression(expr) => self.expr(expr),
+            Stmt::Break | Stmt::Continue | Stmt::Import(_) => {}
+        }
+    }
+
+    fn arms(&self, arms: &mut [(Expr, Vec<Stmt>)], else_body: &mut Option<Vec<Stmt>>) {
+        for (value, body) in arms {
+            self.expr(value);
+            self.apply(body);
+        }
+        if let Some(else_body) = else_body {
+            self.apply(else_body);
+        }
+    }
+
+    fn expr(&self, expr: &mut Expr) {
+        match expr {
+            Expr::Variable(name, _) => self.name(name),
+            Expr::Assign(name, value, _) => {
+                self.name(name);
+                self.expr(value);
+            }
+            Expr::Call(name, args) => {
+                if !self.externals.contains(name) {
+                    self.name(name);
+                }
+                for arg in args {
+                    self.expr(arg);
+                }
+            }
+            Expr::Unary(_, operand) => self.expr(operand),
+            Expr::Binary(left, _, right) | Expr::Index(left, right) | Expr::Range(left, right) => {
+                self.expr(left);
+                self.expr(right);
+            }
+            Expr::Ternary(condition, then_branch, else_branch) => {
+                self.expr(condition);
+                self.expr(then_branch);
+                self.expr(else_branch);
+            }
+            Expr::ArrayLiteral(elements) => {
+                for element in elements {
+                    self.expr(element);
+                }
+            }
+            Expr::Interpolation(parts) => {
+                for part in parts {
+                    if let StringPart::Expr(expr) = part {
+                        self.expr(expr);
+                    }
+                }
+            }
+            Expr::NumberLiteral(..)
+            | Expr::FloatLiteral(_)
+            | Expr::BoolLiteral(_)
+            | Expr::StringLiteral(_)
+            | Expr::Quantity(..) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::{Interpreter, Value};
+    use crate::printer::pretty_print;
+
+    const MODULE: &str = "let gear = 1; function startEngine(rpm = 800) { gear = rpm; log(\"start\"); return helper(rpm); } function helper(x) { return x + 1; }";
+
+    #[test]
+    fn user_names_are_prefixed_and_builtins_kept() {
+        let mut stmts = crate::parse_str(&format!("{} on tick {{ startEngine(); beep(); }}", MODULE)).unwrap();
+        Namespace::new("rear").with_external("beep").apply(&mut stmts);
+        let printed = pretty_print(&stmts);
+        assert!(printed.starts_with("let rear__gear = 1;"), "{}", printed);
+        assert!(printed.contains("function rear__startEngine(rear__rpm = 800) {"), "{}", printed);
+        assert!(printed.contains("return rear__helper(rear__rpm);"), "{}", printed);
+        assert!(printed.contains("log(\"start\");") && printed.contains("beep();"), "{}", printed);
+    }
+
+    #[test]
+    fn two_modules_with_the_same_function_stay_distinct() {
+        let mut front = crate::parse_str(MODULE).unwrap();
+        let mut rear = crate::parse_str(MODULE).unwrap();
+        Namespace::new("front").apply(&mut front);
+        Namespace::new("rear").apply(&mut rear);
+        let mut program = front;
+        program.extend(rear);
+        program.extend(crate::parse_str("r1 = front__startEngine(5); r2 = rear__startEngine();").unwrap());
+        let mut interpreter = Interpreter::new().with_log_sink(|_| {});
+        interpreter.run(&program).unwrap();
+        assert_eq!((interpreter.get("r1"), interpreter.get("r2")), (Some(&Value::Int(6)), Some(&Value::Int(801))));
+        assert_eq!((interpreter.get("front__gear"), interpreter.get("rear__gear")), (Some(&Value::Int(5)), Some(&Value::Int(800))));
+    }
+}
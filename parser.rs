@@ -1,135 +1,1638 @@
-use crate::lexer::{Lexer, Token, TokenType};
+use crate::lexer::{LexError, Lexer, Position, Token, TokenType};
+use std::hash::{Hash, Hasher};
+use thiserror::Error;
 
-#[derive(Debug)]
+#[cfg(feature = "schema")]
+use schemars::JsonSchema;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "serde", serde(tag = "kind", content = "value"))]
 pub enum Expr {
-    NumberLiteral(i32),
-    Variable(String),
+    // The value as written and the width from its suffix, `i32` without one;
+    // the type checker rejects values outside that width.
+    NumberLiteral(i64, NumType),
+    FloatLiteral(f64),
+    BoolLiteral(bool),
+    StringLiteral(String),
+    Quantity(f64, Unit),
+    Variable(String, Position),
+    Unary(String, Box<Expr>),
     Binary(Box<Expr>, String, Box<Expr>),
+    Ternary(Box<Expr>, Box<Expr>, Box<Expr>),
+    Assign(String, Box<Expr>, Position),
+    Call(String, Vec<Expr>),
+    ArrayLiteral(Vec<Expr>),
+    Index(Box<Expr>, Box<Expr>),
+    // A string literal containing `${...}`, evaluated to the concatenation
+    // of its parts.
+    Interpolation(Vec<StringPart>),
+    // `start..end`, the integers from `start` up to but excluding `end`;
+    // only parsed as the subject of a `for` loop.
+    Range(Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "serde", serde(tag = "kind", content = "value"))]
+pub enum StringPart {
+    Literal(String),
+    Expr(Expr),
+}
+
+// Written out so that the positions of variables and assignments don't make
+// otherwise identical trees unequal.
+impl PartialEq for Expr {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Expr::NumberLiteral(a, a_ty), Expr::NumberLiteral(b, b_ty)) => a == b && a_ty == b_ty,
+            (Expr::FloatLiteral(a), Expr::FloatLiteral(b)) => a == b,
+            (Expr::BoolLiteral(a), Expr::BoolLiteral(b)) => a == b,
+            (Expr::StringLiteral(a), Expr::StringLiteral(b)) => a == b,
+            (Expr::Quantity(a, a_unit), Expr::Quantity(b, b_unit)) => a == b && a_unit == b_unit,
+            (Expr::Variable(a, _), Expr::Variable(b, _)) => a == b,
+            (Expr::Unary(a_op, a), Expr::Unary(b_op, b)) => a_op == b_op && a == b,
+            (Expr::Binary(a_left, a_op, a_right), Expr::Binary(b_left, b_op, b_right)) => {
+                a_left == b_left && a_op == b_op && a_right == b_right
+            }
+            (Expr::Ternary(a_cond, a_then, a_else), Expr::Ternary(b_cond, b_then, b_else)) => {
+                a_cond == b_cond && a_then == b_then && a_else == b_else
+            }
+            (Expr::Assign(a, a_value, _), Expr::Assign(b, b_value, _)) => a == b && a_value == b_value,
+            (Expr::Call(a, a_args), Expr::Call(b, b_args)) => a == b && a_args == b_args,
+            (Expr::ArrayLiteral(a), Expr::ArrayLiteral(b)) => a == b,
+            (Expr::Index(a_target, a_index), Expr::Index(b_target, b_index)) => a_target == b_target && a_index == b_index,
+            (Expr::Interpolation(a), Expr::Interpolation(b)) => a == b,
+            (Expr::Range(a_start, a_end), Expr::Range(b_start, b_end)) => a_start == b_start && a_end == b_end,
+            _ => false,
+        }
+    }
+}
+
+// Written out rather than derived because of the `f64` literals, and
+// consistent with `PartialEq` in leaving positions out.
+impl Hash for Expr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Expr::NumberLiteral(value, ty) => {
+                value.hash(state);
+                ty.hash(state);
+            }
+            Expr::FloatLiteral(value) => hash_f64(*value, state),
+            Expr::BoolLiteral(value) => value.hash(state),
+            Expr::StringLiteral(value) => value.hash(state),
+            Expr::Quantity(value, unit) => {
+                hash_f64(*value, state);
+                unit.hash(state);
+            }
+            Expr::Variable(name, _) => name.hash(state),
+            Expr::Unary(op, operand) => {
+                op.hash(state);
+                operand.hash(state);
+            }
+            Expr::Binary(left, op, right) => {
+                left.hash(state);
+                op.hash(state);
+                right.hash(state);
+            }
+            Expr::Ternary(condition, then_branch, else_branch) => {
+                condition.hash(state);
+                then_branch.hash(state);
+                else_branch.hash(state);
+            }
+            Expr::Assign(name, value, _) => {
+                name.hash(state);
+                value.hash(state);
+            }
+            Expr::Call(name, args) => {
+                name.hash(state);
+                args.hash(state);
+            }
+            Expr::ArrayLiteral(elements) => elements.hash(state),
+            Expr::Index(target, index) => {
+                target.hash(state);
+                index.hash(state);
+            }
+            Expr::Interpolation(parts) => parts.hash(state),
+            Expr::Range(start, end) => {
+                start.hash(state);
+                end.hash(state);
+            }
+        }
+    }
+}
+
+// `0.0 == -0.0`, so both must hash alike.
+fn hash_f64<H: Hasher>(value: f64, state: &mut H) {
+    let value = if value == 0.0 { 0.0 } else { value };
+    value.to_bits().hash(state);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub enum NumType {
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+}
+
+impl NumType {
+    pub fn from_suffix(suffix: &str) -> Option<NumType> {
+        match suffix {
+            "i8" => Some(NumType::I8),
+            "i16" => Some(NumType::I16),
+            "i32" => Some(NumType::I32),
+            "i64" => Some(NumType::I64),
+            "u8" => Some(NumType::U8),
+            "u16" => Some(NumType::U16),
+            "u32" => Some(NumType::U32),
+            "u64" => Some(NumType::U64),
+            _ => None,
+        }
+    }
+
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            NumType::I8 => "i8",
+            NumType::I16 => "i16",
+            NumType::I32 => "i32",
+            NumType::I64 => "i64",
+            NumType::U8 => "u8",
+            NumType::U16 => "u16",
+            NumType::U32 => "u32",
+            NumType::U64 => "u64",
+        }
+    }
+
+    // Literals are at most `i64::MAX`, so `u64` only bounds them below.
+    pub fn contains(&self, value: i64) -> bool {
+        match self {
+            NumType::I8 => i8::try_from(value).is_ok(),
+            NumType::I16 => i16::try_from(value).is_ok(),
+            NumType::I32 => i32::try_from(value).is_ok(),
+            NumType::I64 => true,
+            NumType::U8 => u8::try_from(value).is_ok(),
+            NumType::U16 => u16::try_from(value).is_ok(),
+            NumType::U32 => u32::try_from(value).is_ok(),
+            NumType::U64 => value >= 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub enum Unit {
+    KilometersPerHour,
+    MilesPerHour,
+    Meters,
+    Seconds,
+    Bar,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dimension {
+    Speed,
+    Distance,
+    Time,
+    Pressure,
+}
+
+impl Unit {
+    pub fn from_suffix(suffix: &str) -> Option<Unit> {
+        match suffix {
+            "km/h" => Some(Unit::KilometersPerHour),
+            "mph" => Some(Unit::MilesPerHour),
+            "m" => Some(Unit::Meters),
+            "s" => Some(Unit::Seconds),
+            "bar" => Some(Unit::Bar),
+            _ => None,
+        }
+    }
+
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            Unit::KilometersPerHour => "km/h",
+            Unit::MilesPerHour => "mph",
+            Unit::Meters => "m",
+            Unit::Seconds => "s",
+            Unit::Bar => "bar",
+        }
+    }
+
+    pub fn dimension(&self) -> Dimension {
+        match self {
+            Unit::KilometersPerHour | Unit::MilesPerHour => Dimension::Speed,
+            Unit::Meters => Dimension::Distance,
+            Unit::Seconds => Dimension::Time,
+            Unit::Bar => Dimension::Pressure,
+        }
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "serde", serde(tag = "kind", content = "value"))]
 pub enum Stmt {
+    VarDecl(String, Option<Expr>),
+    ConstDecl(String, Expr),
     Assignment(String, Expr),
-    FunctionDeclaration(String, Vec<String>, Vec<Stmt>),
-    If(Box<Expr>, Vec<Stmt>),
+    CompoundAssignment(String, String, Expr),
+    // `i++` or `i--`, adding or subtracting 1. It is a statement only, with
+    // no prefix form and no value.
+    Increment(String, String),
+    // Parameters with an optional default, used when the argument is omitted,
+    // and whether the function was declared `pure`.
+    FunctionDeclaration(String, Vec<(String, Option<Expr>)>, Vec<Stmt>, bool),
+    If(Box<Expr>, Vec<Stmt>, Option<Vec<Stmt>>),
+    // `if` followed by one or more `else if` arms, kept flat rather than as
+    // nested `If`s; the first arm whose condition holds runs.
+    IfChain(Vec<(Expr, Vec<Stmt>)>, Option<Vec<Stmt>>),
+    While(Box<Expr>, Vec<Stmt>),
+    // `for (name in range) { ... }`, binding `name` afresh on each pass.
+    ForRange(String, Expr, Vec<Stmt>),
+    // `for (init; condition; step) { ... }`; a `let` in `init` is scoped to
+    // the loop.
+    ForClassic(Box<Stmt>, Expr, Box<Stmt>, Vec<Stmt>),
+    // Runs the first case whose value equals the subject, or the default;
+    // cases do not fall through.
+    Switch(Expr, Vec<(Expr, Vec<Stmt>)>, Option<Vec<Stmt>>),
+    // A bare `{ ... }`, scoping the variables declared in it.
+    Block(Vec<Stmt>),
+    Break,
+    Continue,
+    Return(Option<Expr>),
+    EventHandler(String, Vec<Stmt>),
+    Expression(Expr),
+    // `import "path";`, replaced by the imported declarations when the
+    // program is read through `loader::load`.
+    Import(String),
+}
+
+const DEFAULT_MAX_DEPTH: usize = 128;
+
+#[derive(Debug, Error)]
+pub enum ParseError {
+    // The token found and a description of what was expected instead.
+    #[error("expected {1}, found {0}")]
+    UnexpectedToken(Token, String),
+    // The token at which nesting exceeded the configured limit.
+    #[error("nesting exceeds the limit of {1} levels")]
+    NestingTooDeep(Token, usize),
+    // The `{` still open when the input ended.
+    #[error("unexpected end of input, '{{' at {}:{} is never closed", .0.line, .0.column)]
+    UnexpectedEof(Token),
+    // An integer literal too large to represent, even before its width
+    // suffix is checked.
+    #[error("integer literal {} out of range", .0.value)]
+    IntegerOutOfRange(Token),
+    // A `}` at the top level, closing nothing.
+    #[error("unmatched '}}'")]
+    UnmatchedBrace(Token),
+    #[error(transparent)]
+    Lex(#[from] LexError),
+}
+
+impl ParseError {
+    pub fn position(&self) -> Position {
+        match self {
+            ParseError::UnexpectedToken(token, _)
+            | ParseError::NestingTooDeep(token, _)
+            | ParseError::UnexpectedEof(token)
+            | ParseError::IntegerOutOfRange(token)
+            | ParseError::UnmatchedBrace(token) => token.position(),
+            ParseError::Lex(err) => Position {
+                line: err.line,
+                column: err.column,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ParserOptions {
+    // A line break may stand in for the `;` ending a simple statement.
+    pub implicit_semicolons: bool,
+    // Custom infix operators and their precedence on the same scale as
+    // `binary_precedence`; they are registered with the lexer as well.
+    pub operators: Vec<(String, u8)>,
+    // How deeply expressions and blocks may nest before parsing gives up
+    // instead of risking a stack overflow.
+    pub max_depth: usize,
+    // Keep comments so `parse_with_comments` can attach them to statements.
+    pub preserve_comments: bool,
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        ParserOptions {
+            implicit_semicolons: false,
+            operators: Vec::new(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            preserve_comments: false,
+        }
+    }
 }
 
 pub struct Parser<'a> {
     lexer: Lexer<'a>,
     tokens: Vec<Token>,
     position: usize,
+    options: ParserOptions,
+    depth: usize,
+    // Whether a line break preceded each token; only tracked with
+    // `implicit_semicolons`, since newline tokens are otherwise never lexed.
+    newline_before: Vec<bool>,
+    // Comments preceding each token; only tracked with `preserve_comments`.
+    comments_before: Vec<Vec<Token>>,
+}
+
+// A top-level statement with the comments around it: `leading` are those
+// on the lines before it, `trailing` those inside it or after it on its
+// last line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommentedStmt {
+    pub leading: Vec<Token>,
+    pub stmt: Stmt,
+    pub trailing: Vec<Token>,
 }
 
 impl<'a> Parser<'a> {
-    pub fn new(lexer: Lexer<'a>) -> Self {
-        let mut parser = Parser {
+    pub fn try_new(lexer: Lexer<'a>) -> Result<Self, LexError> {
+        Self::with_options(lexer, ParserOptions::default())
+    }
+
+    pub fn with_options(lexer: Lexer<'a>, options: ParserOptions) -> Result<Self, LexError> {
+        let lexer = if options.implicit_semicolons { lexer.with_newlines() } else { lexer };
+        let mut lexer = if options.preserve_comments { lexer.with_comments() } else { lexer };
+        for (op, _) in &options.operators {
+            lexer.add_operator(op);
+        }
+        let mut tokens = Vec::new();
+        let mut newline_before = Vec::new();
+        let mut comments_before = Vec::new();
+        let mut after_newline = false;
+        let mut comments = Vec::new();
+        for token in lexer.tokenize()? {
+            match token.token_type {
+                TokenType::Newline => after_newline = true,
+                TokenType::Comment => comments.push(token),
+                _ => {
+                    tokens.push(token);
+                    newline_before.push(after_newline);
+                    comments_before.push(std::mem::take(&mut comments));
+                    after_newline = false;
+                }
+            }
+        }
+        Ok(Parser {
             lexer,
-            tokens: Vec::new(),
+            tokens,
             position: 0,
-        };
-        parser.tokens = parser.lexer.tokenize();
-        parser
+            options,
+            depth: 0,
+            newline_before,
+            comments_before,
+        })
+    }
+
+    pub fn parse(&mut self) -> Result<Vec<Stmt>, ParseError> {
+        let mut stmts = Vec::new();
+        while let Some(stmt) = self.parse_one() {
+            stmts.push(stmt?);
+        }
+        Ok(stmts)
     }
 
-    pub fn parse(&mut self) -> Vec<Stmt> {
+    // Parses as much as possible instead of stopping at the first error: after
+    // a malformed statement, tokens are skipped up to and including the next
+    // `;` or `}` and parsing resumes there.
+    pub fn parse_recovering(&mut self) -> (Vec<Stmt>, Vec<ParseError>) {
         let mut stmts = Vec::new();
+        let mut errors = Vec::new();
+        while let Some(stmt) = self.parse_one() {
+            match stmt {
+                Ok(stmt) => stmts.push(stmt),
+                Err(err) => {
+                    errors.push(err);
+                    self.synchronize();
+                }
+            }
+        }
+        (stmts, errors)
+    }
+
+    fn synchronize(&mut self) {
         while self.current_token().token_type != TokenType::EOF {
-            stmts.push(self.parse_statement());
+            let token = self.consume_token();
+            if token.token_type == TokenType::Delimiter && (token.value == ";" || token.value == "}") {
+                return;
+            }
         }
-        stmts
     }
 
-    fn parse_statement(&mut self) -> Stmt {
+    pub fn parse_with_comments(&mut self) -> Result<Vec<CommentedStmt>, ParseError> {
+        let mut stmts: Vec<CommentedStmt> = Vec::new();
+        let mut leading = self.take_comments(self.position);
+        loop {
+            let start = self.position;
+            let stmt = match self.parse_one() {
+                Some(stmt) => stmt?,
+                None => break,
+            };
+            let end = self.position;
+            let mut trailing: Vec<Token> = (start + 1..end).flat_map(|i| self.take_comments(i)).collect();
+            let last_line = self.tokens[end - 1].line;
+            let (same_line, next): (Vec<Token>, Vec<Token>) =
+                self.take_comments(end).into_iter().partition(|c| c.line == last_line);
+            trailing.extend(same_line);
+            stmts.push(CommentedStmt {
+                leading: std::mem::replace(&mut leading, next),
+                stmt,
+                trailing,
+            });
+        }
+        // Comments after the last statement stay with it.
+        if let Some(last) = stmts.last_mut() {
+            last.trailing.extend(leading);
+        }
+        Ok(stmts)
+    }
+
+    fn take_comments(&mut self, index: usize) -> Vec<Token> {
+        self.comments_before.get_mut(index).map(std::mem::take).unwrap_or_default()
+    }
+
+    // Parses the next top-level statement, or returns None once only EOF
+    // remains. On success the parser is left at the start of the following
+    // statement.
+    pub fn parse_one(&mut self) -> Option<Result<Stmt, ParseError>> {
+        self.depth = 0;
+        if self.current_token().token_type == TokenType::EOF {
+            None
+        } else if self.check(TokenType::Delimiter, "}") {
+            Some(Err(ParseError::UnmatchedBrace(self.current_token().clone())))
+        } else {
+            Some(self.parse_statement())
+        }
+    }
+
+    // Read-only views of the parser's state for tooling: the index of the
+    // next token to parse, every token of the input (ending with EOF), and
+    // the ones not consumed yet.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    pub fn remaining(&self) -> &[Token] {
+        &self.tokens[self.position..]
+    }
+
+    fn parse_statement(&mut self) -> Result<Stmt, ParseError> {
         match self.current_token().token_type {
             TokenType::Keyword if self.current_token().value == "function" => self.parse_function_declaration(),
+            TokenType::Keyword if self.current_token().value == "pure" => self.parse_function_declaration(),
             TokenType::Keyword if self.current_token().value == "if" => self.parse_if_statement(),
-            TokenType::Identifier => self.parse_assignment(),
-            _ => panic!("Unexpected token: {:?}", self.current_token()),
+            TokenType::Keyword if self.current_token().value == "unless" => self.parse_if_statement(),
+            TokenType::Keyword if self.current_token().value == "while" => self.parse_while_statement(),
+            TokenType::Keyword if self.current_token().value == "for" => self.parse_for_statement(),
+            TokenType::Keyword if self.current_token().value == "switch" => self.parse_switch_statement(),
+            TokenType::Keyword if self.current_token().value == "break" => self.parse_loop_control(Stmt::Break),
+            TokenType::Keyword if self.current_token().value == "continue" => self.parse_loop_control(Stmt::Continue),
+            TokenType::Keyword if self.current_token().value == "return" => self.parse_return_statement(),
+            TokenType::Keyword if self.current_token().value == "let" => self.parse_var_declaration(),
+            TokenType::Keyword if self.current_token().value == "const" => self.parse_const_declaration(),
+            TokenType::Keyword if self.current_token().value == "on" => self.parse_event_handler(),
+            TokenType::Keyword if self.current_token().value == "import" => self.parse_import(),
+            TokenType::Delimiter if self.current_token().value == "{" => self.parse_block(),
+            TokenType::Identifier => {
+                let next = self.peek_token(1);
+                match next.value.as_str() {
+                    "(" if next.token_type == TokenType::Delimiter => self.parse_expression_statement(),
+                    _ if self.at_assignment() => self.parse_assignment(),
+                    _ => Err(ParseError::UnexpectedToken(next.clone(), "'=' or '(' after identifier".to_string())),
+                }
+            },
+            _ => Err(self.error("statement")),
+        }
+    }
+
+    fn parse_statement_list(&mut self) -> Result<Vec<Stmt>, ParseError> {
+        self.nested(Self::parse_statements)
+    }
+
+    fn parse_statements(&mut self) -> Result<Vec<Stmt>, ParseError> {
+        let mut stmts = Vec::new();
+        while self.current_token().token_type != TokenType::EOF && !self.check(TokenType::Delimiter, "}") {
+            stmts.push(self.parse_statement()?);
+        }
+        Ok(stmts)
+    }
+
+    fn parse_block(&mut self) -> Result<Stmt, ParseError> {
+        let body = self.parse_braced_body()?;
+        Ok(Stmt::Block(body))
+    }
+
+    fn parse_braced_body(&mut self) -> Result<Vec<Stmt>, ParseError> {
+        let open = self.expect(TokenType::Delimiter, "{")?;
+        let body = self.parse_statement_list()?;
+        self.expect_closing(open)?;
+        Ok(body)
+    }
+
+    // Running out of input is reported against the brace left open, which
+    // says more about the mistake than the end of the file does.
+    fn expect_closing(&mut self, open: Token) -> Result<(), ParseError> {
+        if self.current_token().token_type == TokenType::EOF {
+            return Err(ParseError::UnexpectedEof(open));
         }
+        self.expect(TokenType::Delimiter, "}")?;
+        Ok(())
     }
 
-    fn parse_function_declaration(&mut self) -> Stmt {
-        self.consume_token(); // 'function'
+    fn parse_function_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let pure = self.consume_token().value == "pure"; // 'function' or 'pure'
+        if pure {
+            self.expect(TokenType::Keyword, "function")?;
+        }
+        if self.current_token().token_type != TokenType::Identifier {
+            return Err(self.error("function name"));
+        }
         let name = self.consume_token().value;
-        self.consume_token(); // '('
-        let params = self.parse_parameter_list();
-        self.consume_token(); // ')'
-        self.consume_token(); // '{'
-        let body = self.parse_statement_list();
-        self.consume_token(); // '}'
-        Stmt::FunctionDeclaration(name, params, body)
+        self.expect(TokenType::Delimiter, "(")?;
+        let params = self.parse_parameter_list()?;
+        self.expect(TokenType::Delimiter, ")")?;
+        let body = self.parse_braced_body()?;
+        Ok(Stmt::FunctionDeclaration(name, params, body, pure))
     }
 
-    fn parse_parameter_list(&mut self) -> Vec<String> {
-        let mut params = Vec::new();
-        while self.current_token().token_type != TokenType::Delimiter || self.current_token().value != ")" {
+    // Once a parameter has a default, every later one needs one too.
+    fn parse_parameter_list(&mut self) -> Result<Vec<(String, Option<Expr>)>, ParseError> {
+        let mut params: Vec<(String, Option<Expr>)> = Vec::new();
+        while !self.check(TokenType::Delimiter, ")") {
             if self.current_token().token_type == TokenType::Identifier {
-                params.push(self.consume_token().value);
-            }
-            if self.current_token().token_type == TokenType::Delimiter && self.current_token().value == "," {
+                let name = self.consume_token().value;
+                let default = if self.check(TokenType::Operator, "=") {
+                    self.consume_token(); // '='
+                    Some(self.parse_expression()?)
+                } else if params.last().is_some_and(|(_, default)| default.is_some()) {
+                    return Err(self.error("'=' and a default value"));
+                } else {
+                    None
+                };
+                params.push((name, default));
+            } else if self.check(TokenType::Delimiter, ",") {
                 self.consume_token();
+            } else {
+                return Err(self.error("parameter name or ')'"));
             }
         }
-        params
+        Ok(params)
     }
 
-    fn parse_if_statement(&mut self) -> Stmt {
-        self.consume_token(); // 'if'
-        self.consume_token(); // '('
-        let condition = self.parse_expression();
-        self.consume_token(); // ')'
-        self.consume_token(); // '{'
-        let body = self.parse_statement_list();
-        self.consume_token(); // '}'
-        Stmt::If(Box::new(condition), body)
+    // `unless (c)` is sugar for `if (!c)`.
+    fn parse_if_statement(&mut self) -> Result<Stmt, ParseError> {
+        let mut arms = vec![self.parse_if_arm()?];
+        let mut else_body = None;
+        while self.check(TokenType::Keyword, "else") {
+            self.consume_token(); // 'else'
+            if self.check(TokenType::Keyword, "if") || self.check(TokenType::Keyword, "unless") {
+                arms.push(self.parse_if_arm()?);
+            } else {
+                else_body = Some(self.parse_braced_body()?);
+                break;
+            }
+        }
+        if arms.len() == 1 {
+            let (condition, body) = arms.remove(0);
+            Ok(Stmt::If(Box::new(condition), body, else_body))
+        } else {
+            Ok(Stmt::IfChain(arms, else_body))
+        }
+    }
+
+    fn parse_if_arm(&mut self) -> Result<(Expr, Vec<Stmt>), ParseError> {
+        let negate = self.consume_token().value == "unless"; // 'if' or 'unless'
+        self.expect(TokenType::Delimiter, "(")?;
+        let mut condition = self.parse_expression()?;
+        if negate {
+            condition = Expr::Unary("!".to_string(), Box::new(condition));
+        }
+        self.expect(TokenType::Delimiter, ")")?;
+        let body = self.parse_braced_body()?;
+        Ok((condition, body))
+    }
+
+    fn parse_while_statement(&mut self) -> Result<Stmt, ParseError> {
+        self.consume_token(); // 'while'
+        self.expect(TokenType::Delimiter, "(")?;
+        let condition = self.parse_expression()?;
+        self.expect(TokenType::Delimiter, ")")?;
+        let body = self.parse_braced_body()?;
+        Ok(Stmt::While(Box::new(condition), body))
+    }
+
+    fn parse_for_statement(&mut self) -> Result<Stmt, ParseError> {
+        self.consume_token(); // 'for'
+        self.expect(TokenType::Delimiter, "(")?;
+        if self.peek_token(1).token_type != TokenType::Keyword || self.peek_token(1).value != "in" {
+            return self.parse_classic_for();
+        }
+        if self.current_token().token_type != TokenType::Identifier {
+            return Err(self.error("loop variable"));
+        }
+        let name = self.consume_token().value;
+        self.consume_token(); // 'in'
+        let range = self.parse_range()?;
+        self.expect(TokenType::Delimiter, ")")?;
+        let body = self.parse_braced_body()?;
+        Ok(Stmt::ForRange(name, range, body))
+    }
+
+    // The initializer is a `let` or an assignment ending in `;` as usual; the
+    // step is an assignment or expression ended by the `)`.
+    fn parse_classic_for(&mut self) -> Result<Stmt, ParseError> {
+        let init = match self.current_token().token_type {
+            TokenType::Keyword if self.current_token().value == "let" => self.parse_var_declaration()?,
+            TokenType::Identifier if self.at_assignment() => self.parse_assignment()?,
+            _ => return Err(self.error("loop variable or initializer")),
+        };
+        let condition = self.parse_expression()?;
+        self.expect(TokenType::Delimiter, ";")?;
+        let step = if self.current_token().token_type == TokenType::Identifier && self.at_assignment() {
+            self.parse_assignment_clause()?
+        } else {
+            Stmt::Expression(self.parse_expression()?)
+        };
+        self.expect(TokenType::Delimiter, ")")?;
+        let body = self.parse_braced_body()?;
+        Ok(Stmt::ForClassic(Box::new(init), condition, Box::new(step), body))
+    }
+
+    fn parse_range(&mut self) -> Result<Expr, ParseError> {
+        let start = self.parse_binary(0)?;
+        self.expect(TokenType::Operator, "..")?;
+        let end = self.parse_binary(0)?;
+        Ok(Expr::Range(Box::new(start), Box::new(end)))
+    }
+
+    fn parse_switch_statement(&mut self) -> Result<Stmt, ParseError> {
+        self.consume_token(); // 'switch'
+        self.expect(TokenType::Delimiter, "(")?;
+        let subject = self.parse_expression()?;
+        self.expect(TokenType::Delimiter, ")")?;
+        let open = self.expect(TokenType::Delimiter, "{")?;
+        let mut cases = Vec::new();
+        while self.check(TokenType::Keyword, "case") {
+            self.consume_token(); // 'case'
+            let value = self.parse_expression()?;
+            let body = self.parse_braced_body()?;
+            cases.push((value, body));
+        }
+        let default = if self.check(TokenType::Keyword, "default") {
+            self.consume_token(); // 'default'
+            let body = self.parse_braced_body()?;
+            Some(body)
+        } else {
+            None
+        };
+        self.expect_closing(open)?;
+        Ok(Stmt::Switch(subject, cases, default))
+    }
+
+    fn parse_loop_control(&mut self, stmt: Stmt) -> Result<Stmt, ParseError> {
+        self.consume_token(); // 'break' or 'continue'
+        self.expect_terminator()?;
+        Ok(stmt)
+    }
+
+    fn parse_return_statement(&mut self) -> Result<Stmt, ParseError> {
+        self.consume_token(); // 'return'
+        let value = if self.check(TokenType::Delimiter, ";") || self.at_implicit_terminator() {
+            None
+        } else {
+            Some(self.parse_expression()?)
+        };
+        self.expect_terminator()?;
+        Ok(Stmt::Return(value))
+    }
+
+    fn parse_import(&mut self) -> Result<Stmt, ParseError> {
+        self.consume_token(); // 'import'
+        if self.current_token().token_type != TokenType::String {
+            return Err(self.error("path string after 'import'"));
+        }
+        let path = self.consume_token().value;
+        self.expect_terminator()?;
+        Ok(Stmt::Import(path))
+    }
+
+    fn parse_event_handler(&mut self) -> Result<Stmt, ParseError> {
+        self.consume_token(); // 'on'
+        if self.current_token().token_type != TokenType::Identifier {
+            return Err(self.error("event name"));
+        }
+        let event = self.consume_token().value;
+        let body = self.parse_braced_body()?;
+        Ok(Stmt::EventHandler(event, body))
     }
 
-    fn parse_assignment(&mut self) -> Stmt {
+    fn parse_var_declaration(&mut self) -> Result<Stmt, ParseError> {
+        self.consume_token(); // 'let'
+        if self.current_token().token_type != TokenType::Identifier {
+            return Err(self.error("variable name"));
+        }
         let var_name = self.consume_token().value;
+        let value = if self.check(TokenType::Operator, "=") {
+            self.consume_token(); // '='
+            Some(self.parse_expression()?)
+        } else {
+            None
+        };
+        self.expect_terminator()?;
+        Ok(Stmt::VarDecl(var_name, value))
+    }
+
+    fn parse_const_declaration(&mut self) -> Result<Stmt, ParseError> {
+        self.consume_token(); // 'const'
+        if self.current_token().token_type != TokenType::Identifier {
+            return Err(self.error("constant name"));
+        }
+        let name = self.consume_token().value;
+        if !self.check(TokenType::Operator, "=") {
+            return Err(self.error("'=' initializer for constant"));
+        }
         self.consume_token(); // '='
-        let value = self.parse_expression();
-        self.consume_token(); // ';'
-        Stmt::Assignment(var_name, value)
+        let value = self.parse_expression()?;
+        self.expect_terminator()?;
+        Ok(Stmt::ConstDecl(name, value))
     }
 
-    fn parse_expression(&mut self) -> Expr {
-        let left = self.parse_term();
-        if self.current_token().token_type == TokenType::Operator {
-            let op = self.consume_token().value;
-            let right = self.parse_expression();
-            Expr::Binary(Box::new(left), op, Box::new(right))
+    // Whether the identifier at the current token starts an assignment.
+    fn at_assignment(&self) -> bool {
+        matches!(self.peek_token(1).value.as_str(), "=" | "+=" | "-=" | "*=" | "/=" | "++" | "--")
+    }
+
+    fn parse_assignment(&mut self) -> Result<Stmt, ParseError> {
+        let stmt = self.parse_assignment_clause()?;
+        self.expect_terminator()?;
+        Ok(stmt)
+    }
+
+    fn parse_assignment_clause(&mut self) -> Result<Stmt, ParseError> {
+        let var_name = self.consume_token().value;
+        let op = self.consume_token().value;
+        if op == "++" || op == "--" {
+            return Ok(Stmt::Increment(var_name, op));
+        }
+        let value = self.parse_expression()?;
+        if op == "=" {
+            Ok(Stmt::Assignment(var_name, value))
         } else {
-            left
+            Ok(Stmt::CompoundAssignment(var_name, op, value))
+        }
+    }
+
+    fn parse_expression_statement(&mut self) -> Result<Stmt, ParseError> {
+        let expr = self.parse_expression()?;
+        self.expect_terminator()?;
+        Ok(Stmt::Expression(expr))
+    }
+
+    fn parse_expression(&mut self) -> Result<Expr, ParseError> {
+        self.nested(Self::parse_assign_or_ternary)
+    }
+
+    // `name = value` binds loosest and associates right, so `a = b = 1`
+    // assigns 1 to both.
+    fn parse_assign_or_ternary(&mut self) -> Result<Expr, ParseError> {
+        if self.current_token().token_type == TokenType::Identifier
+            && self.peek_token(1).token_type == TokenType::Operator
+            && self.peek_token(1).value == "="
+        {
+            let token = self.consume_token();
+            self.consume_token(); // '='
+            let value = self.parse_expression()?;
+            return Ok(Expr::Assign(token.value.clone(), Box::new(value), token.position()));
+        }
+        self.parse_ternary()
+    }
+
+    fn parse_ternary(&mut self) -> Result<Expr, ParseError> {
+        let condition = self.parse_binary(0)?;
+        if !self.check(TokenType::Delimiter, "?") {
+            return Ok(condition);
+        }
+        self.consume_token(); // '?'
+        let then_branch = self.parse_expression()?;
+        if !self.check(TokenType::Delimiter, ":") {
+            return Err(self.error("':' in conditional expression"));
+        }
+        self.consume_token(); // ':'
+        let else_branch = self.nested(Self::parse_ternary)?;
+        Ok(Expr::Ternary(Box::new(condition), Box::new(then_branch), Box::new(else_branch)))
+    }
+
+    // Precedence climbing: operators binding tighter than `min_precedence`
+    // are folded into the right operand, so equal levels associate left.
+    // Each operator folded in nests the left operand one level deeper, so a
+    // long flat chain counts against `max_depth` as well.
+    fn parse_binary(&mut self, min_precedence: u8) -> Result<Expr, ParseError> {
+        let depth = self.depth;
+        let result = self.parse_binary_chain(min_precedence);
+        self.depth = depth;
+        result
+    }
+
+    fn parse_binary_chain(&mut self, min_precedence: u8) -> Result<Expr, ParseError> {
+        let mut left = self.parse_unary()?;
+        while self.current_token().token_type == TokenType::Operator {
+            let precedence = match self.precedence(&self.current_token().value) {
+                Some(precedence) if precedence >= min_precedence => precedence,
+                _ => break,
+            };
+            if self.depth >= self.options.max_depth {
+                return Err(ParseError::NestingTooDeep(self.current_token().clone(), self.options.max_depth));
+            }
+            self.depth += 1;
+            let op = self.consume_token().value;
+            let next_precedence = if is_right_associative(&op) { precedence } else { precedence + 1 };
+            let right = self.nested(|parser| parser.parse_binary(next_precedence))?;
+            left = Expr::Binary(Box::new(left), op, Box::new(right));
         }
+        Ok(left)
     }
 
-    fn parse_term(&mut self) -> Expr {
+    fn parse_unary(&mut self) -> Result<Expr, ParseError> {
+        if self.check(TokenType::Operator, "-") || self.check(TokenType::Operator, "!") {
+            let op = self.consume_token().value;
+            let operand = self.nested(Self::parse_unary)?;
+            return Ok(Expr::Unary(op, Box::new(operand)));
+        }
+        self.parse_term()
+    }
+
+    fn parse_term(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.parse_primary()?;
+        while self.check(TokenType::Delimiter, "[") {
+            self.consume_token(); // '['
+            let index = self.parse_expression()?;
+            self.expect(TokenType::Delimiter, "]")?;
+            expr = Expr::Index(Box::new(expr), Box::new(index));
+        }
+        Ok(expr)
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, ParseError> {
         match self.current_token().token_type {
-            TokenType::Number => {
+            TokenType::Number if self.current_token().value.contains(['.', 'e', 'E']) => {
                 let value = self.consume_token().value.parse().unwrap();
-                Expr::NumberLiteral(value)
+                Ok(Expr::FloatLiteral(value))
             },
-            TokenType::Identifier => {
+            TokenType::Number => {
+                let token = self.consume_token();
+                let split = token.value.find(['i', 'u']).unwrap_or(token.value.len());
+                let ty = NumType::from_suffix(&token.value[split..]).unwrap_or(NumType::I32);
+                match token.value[..split].parse() {
+                    Ok(value) => Ok(Expr::NumberLiteral(value, ty)),
+                    Err(_) => Err(ParseError::IntegerOutOfRange(token)),
+                }
+            },
+            TokenType::Quantity => {
+                let text = self.consume_token().value;
+                let split = text.rfind(|c: char| c.is_ascii_digit()).map(|i| i + 1).unwrap_or(text.len());
+                let value = text[..split].parse().unwrap();
+                let unit = Unit::from_suffix(&text[split..]).unwrap();
+                Ok(Expr::Quantity(value, unit))
+            },
+            TokenType::String => {
                 let value = self.consume_token().value;
-                Expr::Variable(value)
+                if self.check(TokenType::Delimiter, "${") {
+                    self.parse_interpolation(value)
+                } else {
+                    Ok(Expr::StringLiteral(value))
+                }
+            },
+            TokenType::Keyword if self.current_token().value == "true" || self.current_token().value == "false" => {
+                let value = self.consume_token().value == "true";
+                Ok(Expr::BoolLiteral(value))
+            },
+            TokenType::Identifier => {
+                let token = self.consume_token();
+                if self.check(TokenType::Delimiter, "(") {
+                    self.parse_call(token.value)
+                } else {
+                    let position = token.position();
+                    Ok(Expr::Variable(token.value, position))
+                }
             },
             TokenType::Delimiter if self.current_token().value == "(" => {
                 self.consume_token(); // '('
-                let expr = self.parse_expression();
-                self.consume_token(); // ')'
-                expr
+                let expr = self.parse_expression()?;
+                self.expect(TokenType::Delimiter, ")")?;
+                Ok(expr)
             },
-            _ => panic!("Unexpected token: {:?}", self.current_token()),
+            TokenType::Delimiter if self.current_token().value == "[" => self.parse_array_literal(),
+            _ => Err(self.error("expression")),
+        }
+    }
+
+    // The lexer follows every interpolation's `}` with a string token for the
+    // text up to the next `${` or the closing quote, so the two alternate.
+    fn parse_interpolation(&mut self, first: String) -> Result<Expr, ParseError> {
+        let mut parts = Vec::new();
+        if !first.is_empty() {
+            parts.push(StringPart::Literal(first));
+        }
+        while self.check(TokenType::Delimiter, "${") {
+            self.consume_token(); // '${'
+            parts.push(StringPart::Expr(self.parse_expression()?));
+            self.expect(TokenType::Delimiter, "}")?;
+            let text = self.consume_token().value;
+            if !text.is_empty() {
+                parts.push(StringPart::Literal(text));
+            }
+        }
+        Ok(Expr::Interpolation(parts))
+    }
+
+    fn parse_array_literal(&mut self) -> Result<Expr, ParseError> {
+        self.consume_token(); // '['
+        let mut elements = Vec::new();
+        if !self.check(TokenType::Delimiter, "]") {
+            loop {
+                elements.push(self.parse_expression()?);
+                if self.check(TokenType::Delimiter, ",") {
+                    self.consume_token();
+                } else {
+                    break;
+                }
+            }
+        }
+        self.expect(TokenType::Delimiter, "]")?;
+        Ok(Expr::ArrayLiteral(elements))
+    }
+
+    fn parse_call(&mut self, name: String) -> Result<Expr, ParseError> {
+        self.consume_token(); // '('
+        let args = self.parse_argument_list()?;
+        self.expect(TokenType::Delimiter, ")")?;
+        Ok(Expr::Call(name, args))
+    }
+
+    fn parse_argument_list(&mut self) -> Result<Vec<Expr>, ParseError> {
+        let mut args = Vec::new();
+        if self.check(TokenType::Delimiter, ")") {
+            return Ok(args);
+        }
+        // Every ',' must be followed by another argument, so `f(a,)` is
+        // rejected by parse_expression rather than silently accepted; a
+        // missing ',' leaves the next argument where ')' is expected.
+        loop {
+            args.push(self.parse_expression()?);
+            if self.check(TokenType::Delimiter, ",") {
+                self.consume_token();
+            } else {
+                return Ok(args);
+            }
+        }
+    }
+
+    fn expect(&mut self, token_type: TokenType, value: &str) -> Result<Token, ParseError> {
+        if self.check(token_type, value) {
+            Ok(self.consume_token())
+        } else {
+            Err(self.error(&format!("'{}'", value)))
+        }
+    }
+
+    fn precedence(&self, op: &str) -> Option<u8> {
+        binary_precedence(op).or_else(|| {
+            self.options
+                .operators
+                .iter()
+                .find(|(custom, _)| custom == op)
+                .map(|&(_, precedence)| precedence)
+        })
+    }
+
+    fn expect_terminator(&mut self) -> Result<(), ParseError> {
+        if self.check(TokenType::Delimiter, ";") {
+            self.consume_token();
+            Ok(())
+        } else if self.at_implicit_terminator() {
+            Ok(())
+        } else {
+            Err(self.error("';'"))
+        }
+    }
+
+    // With implicit semicolons a statement also ends at a line break, a
+    // closing '}' or the end of input.
+    fn at_implicit_terminator(&self) -> bool {
+        self.options.implicit_semicolons
+            && (self.newline_before.get(self.position).copied().unwrap_or(false)
+                || self.check(TokenType::Delimiter, "}")
+                || self.current_token().token_type == TokenType::EOF)
+    }
+
+    fn error(&self, expected: &str) -> ParseError {
+        ParseError::UnexpectedToken(self.current_token().clone(), expected.to_string())
+    }
+
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T, ParseError>) -> Result<T, ParseError> {
+        if self.depth >= self.options.max_depth {
+            return Err(ParseError::NestingTooDeep(self.current_token().clone(), self.options.max_depth));
         }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
     }
 
     fn current_token(&self) -> &Token {
-        &self.tokens[self.position]
+        self.peek_token(0)
+    }
+
+    // The token `n` places after the current one; looking past the end
+    // yields the trailing EOF token.
+    fn peek_token(&self, n: usize) -> &Token {
+        let last = self.tokens.len() - 1;
+        &self.tokens[(self.position + n).min(last)]
+    }
+
+    fn check(&self, token_type: TokenType, value: &str) -> bool {
+        let token = self.current_token();
+        token.token_type == token_type && token.value == value
     }
 
+    // Never advances past EOF, so a caller that over-consumes keeps seeing
+    // EOF rather than indexing out of bounds.
     fn consume_token(&mut self) -> Token {
         let token = self.current_token().clone();
-        self.position += 1;
+        if token.token_type != TokenType::EOF {
+            self.position += 1;
+        }
         token
     }
 }
+
+pub(crate) fn binary_precedence(op: &str) -> Option<u8> {
+    match op {
+        "||" => Some(1),
+        "&&" => Some(2),
+        "==" | "!=" | "<" | ">" | "<=" | ">=" => Some(3),
+        "+" | "-" => Some(4),
+        "*" | "/" | "%" => Some(5),
+        "**" => Some(6),
+        _ => None,
+    }
+}
+
+// `2 ** 3 ** 2` is `2 ** (3 ** 2)`.
+pub(crate) fn is_right_associative(op: &str) -> bool {
+    op == "**"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Result<Vec<Stmt>, ParseError> {
+        Parser::try_new(Lexer::new(source))?.parse()
+    }
+
+    // The token found and the description of what was expected instead.
+    fn unexpected(source: &str) -> (Token, String) {
+        match parse(source) {
+            Err(ParseError::UnexpectedToken(token, expected)) => (token, expected),
+            result => panic!("expected an unexpected token error: {:?}", result),
+        }
+    }
+
+    // The value assigned by `x = <source>;`.
+    fn expr(source: &str) -> Expr {
+        match parse(&format!("x = {};", source)).unwrap().remove(0) {
+            Stmt::Assignment(_, value) => value,
+            stmt => panic!("not an assignment: {:?}", stmt),
+        }
+    }
+
+    fn num(value: i64) -> Expr {
+        Expr::NumberLiteral(value, NumType::I32)
+    }
+
+    fn var(name: &str) -> Expr {
+        Expr::Variable(name.into(), Position::default())
+    }
+
+    fn binary(left: Expr, op: &str, right: Expr) -> Expr {
+        Expr::Binary(Box::new(left), op.into(), Box::new(right))
+    }
+
+    #[test]
+    fn calls_are_expression_statements() {
+        assert_eq!(parse("applyBrakes();").unwrap(), vec![Stmt::Expression(Expr::Call("applyBrakes".into(), vec![]))]);
+        assert_eq!(parse("setSpeed(100);").unwrap(), vec![Stmt::Expression(Expr::Call("setSpeed".into(), vec![num(100)]))]);
+    }
+
+    #[test]
+    fn lex_errors_are_returned_not_raised() {
+        assert!(matches!(Parser::try_new(Lexer::new("x = \"open;")), Err(LexError { .. })));
+        assert!(matches!(parse("x = 3.;"), Err(ParseError::Lex(_))));
+    }
+
+    #[test]
+    fn malformed_input_is_a_descriptive_error() {
+        let err = parse("if (x >").unwrap_err();
+        assert!(matches!(&err, ParseError::UnexpectedToken(token, expected) if token.token_type == TokenType::EOF && expected == "expression"), "{:?}", err);
+        let err = parse("function 123() {}").unwrap_err();
+        assert!(matches!(&err, ParseError::UnexpectedToken(token, expected) if token.value == "123" && expected == "function name"), "{:?}", err);
+        assert_eq!(err.to_string(), "expected function name, found number \"123\"");
+    }
+
+    #[test]
+    fn deeply_nested_parentheses_are_an_error() {
+        let source = format!("x = {}1;", "(".repeat(10000));
+        assert!(matches!(parse(&source), Err(ParseError::NestingTooDeep(_, DEFAULT_MAX_DEPTH))));
+    }
+
+    #[test]
+    fn long_operator_chain_is_an_error() {
+        let source = format!("x = 1{};", " + 1".repeat(400_000));
+        assert!(matches!(parse(&source), Err(ParseError::NestingTooDeep(_, DEFAULT_MAX_DEPTH))));
+        let source = format!("x = 1{};", " + 1".repeat(100));
+        assert!(parse(&source).is_ok());
+    }
+
+    #[test]
+    fn max_depth_is_configurable() {
+        let options = ParserOptions {
+            max_depth: 3,
+            ..ParserOptions::default()
+        };
+        let mut parser = Parser::with_options(Lexer::new("x = (((1)));"), options.clone()).unwrap();
+        assert!(matches!(parser.parse(), Err(ParseError::NestingTooDeep(_, 3))));
+        let mut parser = Parser::with_options(Lexer::new("x = ((1));"), options).unwrap();
+        assert!(parser.parse().is_ok());
+    }
+
+    #[test]
+    fn subtraction_is_left_associative() {
+        assert_eq!(expr("1 - 2 - 3"), binary(binary(num(1), "-", num(2)), "-", num(3)));
+        assert_eq!(expr("8 / 4 / 2"), binary(binary(num(8), "/", num(4)), "/", num(2)));
+    }
+
+    #[test]
+    fn multiplication_binds_tighter_than_addition() {
+        assert_eq!(expr("a + b * c"), binary(var("a"), "+", binary(var("b"), "*", var("c"))));
+        assert_eq!(expr("speed > 60 + 10"), binary(var("speed"), ">", binary(num(60), "+", num(10))));
+    }
+
+    #[test]
+    fn booleans_work_in_assignments_and_conditions() {
+        assert_eq!(parse("running = true;").unwrap(), vec![Stmt::Assignment("running".into(), Expr::BoolLiteral(true))]);
+        assert_eq!(
+            parse("if (running) { x = false; }").unwrap(),
+            vec![Stmt::If(Box::new(var("running")), vec![Stmt::Assignment("x".into(), Expr::BoolLiteral(false))], None)]
+        );
+    }
+
+    #[test]
+    fn string_literals_parse() {
+        assert_eq!(parse("name = \"Model3\";").unwrap(), vec![Stmt::Assignment("name".into(), Expr::StringLiteral("Model3".into()))]);
+        assert_eq!(
+            parse("setMode(\"sport\");").unwrap(),
+            vec![Stmt::Expression(Expr::Call("setMode".into(), vec![Expr::StringLiteral("sport".into())]))]
+        );
+    }
+
+    #[test]
+    fn if_statements_take_else_and_else_if() {
+        let assign = |value| vec![Stmt::Assignment("x".into(), num(value))];
+        assert_eq!(parse("if (a) { x = 1; }").unwrap(), vec![Stmt::If(Box::new(var("a")), assign(1), None)]);
+        assert_eq!(
+            parse("if (a) { x = 1; } else { x = 2; }").unwrap(),
+            vec![Stmt::If(Box::new(var("a")), assign(1), Some(assign(2)))]
+        );
+        assert_eq!(
+            parse("if (a) { x = 1; } else if (b) { x = 2; } else { x = 3; }").unwrap(),
+            vec![Stmt::IfChain(vec![(var("a"), assign(1)), (var("b"), assign(2))], Some(assign(3)))]
+        );
+    }
+
+    #[test]
+    fn while_loops_parse() {
+        assert_eq!(
+            parse("while (speed > 60) { applyBrakes(); }").unwrap(),
+            vec![Stmt::While(
+                Box::new(binary(var("speed"), ">", num(60))),
+                vec![Stmt::Expression(Expr::Call("applyBrakes".into(), vec![]))]
+            )]
+        );
+    }
+
+    #[test]
+    fn return_takes_an_optional_value() {
+        assert_eq!(parse("return;").unwrap(), vec![Stmt::Return(None)]);
+        assert_eq!(
+            parse("function f() { return speed + 10; }").unwrap(),
+            vec![Stmt::FunctionDeclaration("f".into(), vec![], vec![Stmt::Return(Some(binary(var("speed"), "+", num(10))))], false)]
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn ast_round_trips_through_json() {
+        let source = "
+        function startEngine() {
+            speed = 100;
+            if (speed > 60) {
+                applyBrakes();
+            }
+        }
+    ";
+        let ast = parse(source).unwrap();
+        let json = serde_json::to_string(&ast).unwrap();
+        assert!(json.contains("\"kind\":\"FunctionDeclaration\""), "{}", json);
+        assert_eq!(serde_json::from_str::<Vec<Stmt>>(&json).unwrap(), ast);
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        let json = serde_json::to_string(&tokens).unwrap();
+        assert_eq!(serde_json::from_str::<Vec<Token>>(&json).unwrap(), tokens);
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(expr("a && b || c"), binary(binary(var("a"), "&&", var("b")), "||", var("c")));
+        assert_eq!(expr("a || b && c"), binary(var("a"), "||", binary(var("b"), "&&", var("c"))));
+        assert_eq!(expr("speed > 60 && brakesOk"), binary(binary(var("speed"), ">", num(60)), "&&", var("brakesOk")));
+    }
+
+    #[test]
+    fn unary_operators_bind_to_the_nearest_operand() {
+        let unary = |op: &str, operand| Expr::Unary(op.into(), Box::new(operand));
+        assert_eq!(expr("-5 + 3"), binary(unary("-", num(5)), "+", num(3)));
+        assert_eq!(expr("!a && b"), binary(unary("!", var("a")), "&&", var("b")));
+        assert_eq!(expr("1 - -2"), binary(num(1), "-", unary("-", num(2))));
+    }
+
+    #[test]
+    fn let_declares_and_plain_assignment_mutates() {
+        assert_eq!(
+            parse("let speed; let speed = 100; speed = 100;").unwrap(),
+            vec![
+                Stmt::VarDecl("speed".into(), None),
+                Stmt::VarDecl("speed".into(), Some(num(100))),
+                Stmt::Assignment("speed".into(), num(100)),
+            ]
+        );
+    }
+
+    #[test]
+    fn compound_assignments_parse() {
+        assert_eq!(
+            parse("speed += 10; fuel -= 5;").unwrap(),
+            vec![
+                Stmt::CompoundAssignment("speed".into(), "+=".into(), num(10)),
+                Stmt::CompoundAssignment("fuel".into(), "-=".into(), num(5)),
+            ]
+        );
+    }
+
+    #[test]
+    fn quantities_parse_with_their_unit() {
+        assert_eq!(expr("100km/h"), Expr::Quantity(100.0, Unit::KilometersPerHour));
+        assert_eq!(expr("2.5m"), Expr::Quantity(2.5, Unit::Meters));
+    }
+
+    #[test]
+    fn event_handlers_parse() {
+        assert_eq!(
+            parse("on brakePressed { speed = 0; }").unwrap(),
+            vec![Stmt::EventHandler("brakePressed".into(), vec![Stmt::Assignment("speed".into(), num(0))])]
+        );
+    }
+
+    #[test]
+    fn call_arguments_are_full_expressions() {
+        let call = |name: &str, args| Expr::Call(name.into(), args);
+        assert_eq!(expr("clamp(min(a, b), 100)"), call("clamp", vec![call("min", vec![var("a"), var("b")]), num(100)]));
+        assert_eq!(expr("max(a, b) + 1"), binary(call("max", vec![var("a"), var("b")]), "+", num(1)));
+    }
+
+    #[test]
+    fn trailing_comma_in_arguments_is_an_error() {
+        let err = parse("f(a,);").unwrap_err();
+        assert!(matches!(&err, ParseError::UnexpectedToken(token, expected) if token.value == ")" && expected == "expression"), "{:?}", err);
+    }
+
+    #[test]
+    fn arrays_and_indexing_parse() {
+        let index = |target, at| Expr::Index(Box::new(target), Box::new(at));
+        assert_eq!(expr("[0, 10, 20]"), Expr::ArrayLiteral(vec![num(0), num(10), num(20)]));
+        assert_eq!(expr("[]"), Expr::ArrayLiteral(vec![]));
+        assert_eq!(expr("waypoints[1]"), index(var("waypoints"), num(1)));
+        assert_eq!(expr("grid[0][1]"), index(index(var("grid"), num(0)), num(1)));
+    }
+
+    #[test]
+    fn const_requires_an_initializer() {
+        assert_eq!(parse("const MAX_SPEED = 130;").unwrap(), vec![Stmt::ConstDecl("MAX_SPEED".into(), num(130))]);
+        assert!(parse("const X;").is_err());
+    }
+
+    #[test]
+    fn modulo_is_multiplicative() {
+        assert_eq!(expr("angle % 360"), binary(var("angle"), "%", num(360)));
+        assert_eq!(expr("a + b % c"), binary(var("a"), "+", binary(var("b"), "%", var("c"))));
+    }
+
+    #[test]
+    fn ternary_parses_below_everything_but_assignment() {
+        let ternary = |cond, then, otherwise| Expr::Ternary(Box::new(cond), Box::new(then), Box::new(otherwise));
+        assert_eq!(expr("fast ? 2 : 1"), ternary(var("fast"), num(2), num(1)));
+        assert_eq!(expr("a > 1 ? b : c"), ternary(binary(var("a"), ">", num(1)), var("b"), var("c")));
+        assert_eq!(expr("a ? b : c ? d : e"), ternary(var("a"), var("b"), ternary(var("c"), var("d"), var("e"))));
+    }
+
+    #[test]
+    fn identical_programs_parse_equal() {
+        let source = "function f(a) { if (a > 1) { g(a, \"x\"); } else { return [a][0]; } }";
+        assert_eq!(parse(source).unwrap(), parse(source).unwrap());
+        assert_ne!(parse("x = 1;").unwrap(), parse("x = 2;").unwrap());
+        assert_eq!(Lexer::new("x").tokenize().unwrap(), Lexer::new("x").tokenize().unwrap());
+    }
+
+    #[test]
+    fn peek_past_the_end_is_eof() {
+        let parser = Parser::try_new(Lexer::new("if (a)")).unwrap();
+        assert_eq!(parser.peek_token(1).value, "(");
+        assert_eq!(parser.peek_token(3).value, ")");
+        assert_eq!(parser.peek_token(4).token_type, TokenType::EOF);
+        assert_eq!(parser.peek_token(100).token_type, TokenType::EOF);
+        assert!(parser.check(TokenType::Keyword, "if"));
+        assert!(!parser.check(TokenType::Identifier, "if"));
+    }
+
+    #[test]
+    fn consuming_past_the_end_keeps_returning_eof() {
+        let mut parser = Parser::try_new(Lexer::new("x")).unwrap();
+        assert_eq!(parser.consume_token().value, "x");
+        for _ in 0..3 {
+            assert_eq!(parser.consume_token().token_type, TokenType::EOF);
+        }
+        assert_eq!(parser.current_token().token_type, TokenType::EOF);
+    }
+
+    #[test]
+    fn missing_structural_tokens_are_named() {
+        let (token, expected) = unexpected("if (a { f(); }");
+        assert_eq!((expected.as_str(), token.value.as_str()), ("')'", "{"));
+        let (token, expected) = unexpected("x = 1\ny = 2;");
+        assert_eq!((expected.as_str(), token.value.as_str(), token.line), ("';'", "y", 2));
+        assert_eq!(unexpected("function f( { }").1, "parameter name or ')'");
+    }
+
+    #[test]
+    fn argument_lists_parse_expressions() {
+        let call = |name: &str, args| Stmt::Expression(Expr::Call(name.into(), args));
+        assert_eq!(parse("f();").unwrap(), vec![call("f", vec![])]);
+        assert_eq!(parse("f(1);").unwrap(), vec![call("f", vec![num(1)])]);
+        assert_eq!(
+            parse("f(a, b + 1, g(2));").unwrap(),
+            vec![call("f", vec![var("a"), binary(var("b"), "+", num(1)), Expr::Call("g".into(), vec![num(2)])])]
+        );
+        assert_eq!(unexpected("f(a,);").1, "expression");
+        assert_eq!(unexpected("f(a b);").1, "')'");
+    }
+
+    #[test]
+    fn newlines_can_stand_in_for_semicolons() {
+        let implicit = |source| {
+            let options = ParserOptions {
+                implicit_semicolons: true,
+                ..ParserOptions::default()
+            };
+            Parser::with_options(Lexer::new(source), options).unwrap().parse()
+        };
+        let with = parse("function f() {\n speed = 100;\n if (speed > 60) { applyBrakes(); }\n return;\n}\nlet x = 1;\nx += 2;\nf();").unwrap();
+        let without = implicit("function f() {\n speed = 100\n if (speed > 60) { applyBrakes() }\n return\n}\nlet x = 1\nx += 2\nf()").unwrap();
+        assert_eq!(with, without);
+        assert!(implicit("x = 1 y = 2").is_err());
+        assert!(parse("x = 1\ny = 2").is_err());
+    }
+
+    #[test]
+    fn parse_one_yields_each_statement_then_none() {
+        let mut parser = Parser::try_new(Lexer::new("x = 1; f(); let y;")).unwrap();
+        assert!(matches!(parser.parse_one(), Some(Ok(Stmt::Assignment(..)))));
+        assert!(matches!(parser.parse_one(), Some(Ok(Stmt::Expression(..)))));
+        assert!(matches!(parser.parse_one(), Some(Ok(Stmt::VarDecl(..)))));
+        assert!(parser.parse_one().is_none());
+        assert!(parser.parse_one().is_none());
+    }
+
+    #[test]
+    fn assignment_is_an_expression() {
+        let assign = |name: &str, value| Expr::Assign(name.into(), Box::new(value), Position::default());
+        assert_eq!(
+            parse("while ((s = readSpeed()) > 0) { f(); }").unwrap(),
+            vec![Stmt::While(
+                Box::new(binary(assign("s", Expr::Call("readSpeed".into(), vec![])), ">", num(0))),
+                vec![Stmt::Expression(Expr::Call("f".into(), vec![]))]
+            )]
+        );
+        assert_eq!(expr("b = 1"), assign("b", num(1)));
+        assert_eq!(expr("(a == 1)"), binary(var("a"), "==", num(1)));
+    }
+
+    #[test]
+    fn custom_operators_parse_at_their_precedence() {
+        let options = ParserOptions {
+            operators: vec![("~>".to_string(), 3)],
+            ..ParserOptions::default()
+        };
+        let mut parser = Parser::with_options(Lexer::new("x = speed ~> 60 + 1; y = a <= b;"), options).unwrap();
+        let ast = parser.parse().unwrap();
+        assert_eq!(ast[0], Stmt::Assignment("x".into(), binary(var("speed"), "~>", binary(num(60), "+", num(1)))));
+        assert_eq!(ast[1], Stmt::Assignment("y".into(), binary(var("a"), "<=", var("b"))));
+    }
+
+    #[test]
+    fn unless_is_a_negated_if() {
+        assert_eq!(parse("unless (x) {}").unwrap(), parse("if (!x) {}").unwrap());
+        assert_eq!(
+            parse("unless (a && b) { f(); } else unless (c) { g(); } else { h(); }").unwrap(),
+            parse("if (!(a && b)) { f(); } else if (!c) { g(); } else { h(); }").unwrap()
+        );
+    }
+
+    #[test]
+    fn comments_attach_to_the_nearest_statement() {
+        let source = "// note\nx = 1; // after x\n/* block */ f(/* arg */ 2);\n// tail";
+        let options = ParserOptions {
+            preserve_comments: true,
+            ..ParserOptions::default()
+        };
+        let stmts = Parser::with_options(Lexer::new(source), options.clone()).unwrap().parse_with_comments().unwrap();
+        let text = |comments: &[Token]| comments.iter().map(|token| token.value.clone()).collect::<Vec<_>>();
+        assert_eq!(stmts.len(), 2);
+        assert_eq!(text(&stmts[0].leading), ["// note"]);
+        assert!(matches!(&stmts[0].stmt, Stmt::Assignment(name, _) if name == "x"));
+        assert_eq!(text(&stmts[0].trailing), ["// after x"]);
+        assert_eq!(text(&stmts[1].leading), ["/* block */"]);
+        assert_eq!(text(&stmts[1].trailing), ["/* arg */", "// tail"]);
+        assert_eq!(Parser::with_options(Lexer::new(source), options).unwrap().parse().unwrap(), parse(source).unwrap());
+    }
+
+    #[test]
+    fn bare_braces_parse_as_a_block() {
+        assert_eq!(
+            parse("{ let t = 1; f(t); }").unwrap(),
+            vec![Stmt::Block(vec![
+                Stmt::VarDecl("t".into(), Some(num(1))),
+                Stmt::Expression(Expr::Call("f".into(), vec![var("t")])),
+            ])]
+        );
+    }
+
+    #[test]
+    fn else_if_chains_are_flat() {
+        let ast = parse("if (a) { x = 1; } else if (b) { x = 2; } else unless (c) { x = 3; } else { x = 4; }").unwrap();
+        assert!(matches!(&ast[..], [Stmt::IfChain(arms, Some(otherwise))] if arms.len() == 3 && otherwise.len() == 1));
+        assert!(matches!(&parse("if (a) { } else { }").unwrap()[..], [Stmt::If(..)]));
+    }
+
+    #[test]
+    fn recovery_keeps_the_well_formed_statements() {
+        let (stmts, errors) = Parser::try_new(Lexer::new("x = 1; y = = 2; f();")).unwrap().parse_recovering();
+        assert_eq!((stmts.len(), errors.len()), (2, 1));
+        assert!(matches!(&stmts[1], Stmt::Expression(Expr::Call(name, _)) if name == "f"));
+        let (stmts, errors) = Parser::try_new(Lexer::new("function f() { x = ; } g();")).unwrap().parse_recovering();
+        assert_eq!((stmts.len(), errors.len()), (1, 2));
+    }
+
+    #[test]
+    fn switch_parses_cases_and_default() {
+        let ast = parse("switch (driveMode) { case 1 { x = 1; } case 2 { x = 2; } default { x = 0; } }").unwrap();
+        let assign = |value| vec![Stmt::Assignment("x".into(), num(value))];
+        assert_eq!(ast, vec![Stmt::Switch(var("driveMode"), vec![(num(1), assign(1)), (num(2), assign(2))], Some(assign(0)))]);
+        assert!(parse("switch (a) { default { } case 1 { } }").is_err());
+    }
+
+    #[test]
+    fn parameters_take_defaults() {
+        assert_eq!(
+            parse("function f(a, force = 50) { }").unwrap(),
+            vec![Stmt::FunctionDeclaration("f".into(), vec![("a".into(), None), ("force".into(), Some(num(50)))], vec![], false)]
+        );
+        assert!(parse("function f(a = 1, b) { }").is_err());
+    }
+
+    #[test]
+    fn power_is_right_associative_and_binds_tightest() {
+        assert_eq!(expr("2 ** 3 ** 2"), binary(num(2), "**", binary(num(3), "**", num(2))));
+        assert_eq!(expr("a * b ** 2"), binary(var("a"), "*", binary(var("b"), "**", num(2))));
+    }
+
+    #[test]
+    fn stray_closing_brace_is_an_error() {
+        let err = parse("on start { f(); } }\nf();").unwrap_err();
+        assert!(matches!(err, ParseError::UnmatchedBrace(_)), "{:?}", err);
+        assert_eq!(err.to_string(), "unmatched '}'");
+        assert_eq!(err.position(), Position { line: 1, column: 19 });
+    }
+
+    #[test]
+    fn missing_closing_brace_is_unexpected_eof() {
+        let err = parse("f();\nwhile (a) {\n  if (b) { f(); }\n").unwrap_err();
+        assert!(matches!(err, ParseError::UnexpectedEof(_)), "{:?}", err);
+        assert_eq!(err.to_string(), "unexpected end of input, '{' at 2:11 is never closed");
+        assert!(matches!(parse("if (a) { } else {"), Err(ParseError::UnexpectedEof(_))));
+    }
+
+    #[test]
+    fn interpolated_strings_split_into_parts() {
+        assert_eq!(
+            expr("\"speed=${speed}!\""),
+            Expr::Interpolation(vec![
+                StringPart::Literal("speed=".into()),
+                StringPart::Expr(var("speed")),
+                StringPart::Literal("!".into()),
+            ])
+        );
+        assert!(matches!(parse("x = \"${y\";"), Err(ParseError::Lex(_))));
+    }
+
+    #[test]
+    fn for_in_takes_a_range() {
+        assert_eq!(
+            parse("for (i in 0..3) { f(i); }").unwrap(),
+            vec![Stmt::ForRange(
+                "i".into(),
+                Expr::Range(Box::new(num(0)), Box::new(num(3))),
+                vec![Stmt::Expression(Expr::Call("f".into(), vec![var("i")]))]
+            )]
+        );
+        assert!(parse("x = 0..3;").is_err());
+    }
+
+    #[test]
+    fn classic_for_has_three_clauses() {
+        let ast = parse("for (let i = 0; i < 3; i += 1) { f(i); }").unwrap();
+        assert!(matches!(&ast[0], Stmt::ForClassic(init, _, step, body)
+            if matches!(**init, Stmt::VarDecl(..)) && matches!(**step, Stmt::CompoundAssignment(..)) && body.len() == 1));
+        assert!(parse("for (i < 3; i += 1) { }").is_err());
+        assert!(parse("for (let i = 0; i < 3; i += 1;) { }").is_err());
+    }
+
+    #[test]
+    fn oversized_integer_literal_is_an_error() {
+        let err = parse("x =\n  99999999999999999999;").unwrap_err();
+        assert!(matches!(&err, ParseError::IntegerOutOfRange(token) if token.value == "99999999999999999999"), "{:?}", err);
+        assert_eq!(err.position(), Position { line: 2, column: 3 });
+        assert!(parse("x = 9223372036854775807i64;").is_ok());
+    }
+
+    #[test]
+    fn angle_brackets_parse_as_not_equal() {
+        assert_eq!(expr("a <> b"), expr("a != b"));
+    }
+
+    #[test]
+    fn position_tracks_consumed_tokens() {
+        let mut parser = Parser::try_new(Lexer::new("x = 1; f(x);")).unwrap();
+        assert_eq!((parser.position(), parser.tokens().len(), parser.remaining().len()), (0, 10, 10));
+        parser.parse_one().unwrap().unwrap();
+        assert_eq!(parser.position(), 4);
+        let remaining: Vec<_> = parser.remaining().iter().map(|token| token.value.as_str()).collect();
+        assert_eq!(remaining, ["f", "(", "x", ")", ";", ""]);
+        parser.parse_one().unwrap().unwrap();
+        assert!(parser.parse_one().is_none());
+        assert_eq!((parser.position(), parser.remaining().len()), (9, 1));
+    }
+
+    #[test]
+    fn increments_are_postfix_statements() {
+        assert_eq!(parse("i++; j--;").unwrap(), vec![Stmt::Increment("i".into(), "++".into()), Stmt::Increment("j".into(), "--".into())]);
+        assert!(parse("x = i++;").is_err());
+        assert!(parse("++i;").is_err());
+    }
+}
@@ -0,0 +1,160 @@
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::fold;
+use crate::lexer::Position;
+use crate::lints::{self, Lint};
+use crate::parser::Stmt;
+use crate::resolver::{self, ResolveError};
+use crate::typeck;
+
+// One step of a `Pipeline`: checks or rewrites the program, reporting what
+// it finds into `diags`.
+pub trait Pass {
+    fn run(&self, ast: &mut Vec<Stmt>, diags: &mut Vec<Diagnostic>);
+
+    // Whether the pass rewrites the AST rather than only checking it.
+    fn rewrites(&self) -> bool {
+        false
+    }
+}
+
+// Runs its passes in the order they were added. Every check runs, so each
+// contributes its diagnostics, but once an error-severity diagnostic has
+// been reported the pipeline stops before the first pass that rewrites the
+// AST, since it would only transform a program known to be broken.
+#[derive(Default)]
+pub struct Pipeline {
+    passes: Vec<Box<dyn Pass>>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_pass(mut self, pass: impl Pass + 'static) -> Self {
+        self.passes.push(Box::new(pass));
+        self
+    }
+
+    pub fn run(&self, ast: &mut Vec<Stmt>) -> Vec<Diagnostic> {
+        let mut diags: Vec<Diagnostic> = Vec::new();
+        for pass in &self.passes {
+            if pass.rewrites() && diags.iter().any(|diag| diag.severity == Severity::Error) {
+                break;
+            }
+            pass.run(ast, &mut diags);
+        }
+        diags
+    }
+}
+
+// Name resolution; every finding is an error.
+pub struct Resolve;
+
+impl Pass for Resolve {
+    fn run(&self, ast: &mut Vec<Stmt>, diags: &mut Vec<Diagnostic>) {
+        for err in resolver::resolve(ast) {
+            let (message, position) = match err {
+                ResolveError::UndefinedVariable(name, position) => (format!("undefined variable {}", name), Some(position)),
+                ResolveError::CannotReassignConst(name) => (format!("cannot reassign constant {}", name), None),
+                ResolveError::UndeclaredAssignment(name) => (format!("assignment to undeclared variable {}", name), None),
+                ResolveError::BreakOutsideLoop => ("'break' outside of a loop".to_string(), None),
+                ResolveError::ContinueOutsideLoop => ("'continue' outside of a loop".to_string(), None),
+            };
+            diags.push(diagnostic(Severity::Error, message, position));
+        }
+    }
+}
+
+// Type checking; every finding is an error.
+pub struct TypeCheck;
+
+impl Pass for TypeCheck {
+    fn run(&self, ast: &mut Vec<Stmt>, diags: &mut Vec<Diagnostic>) {
+        for err in typeck::check(ast) {
+            diags.push(diagnostic(Severity::Error, err.to_string(), err.position));
+        }
+    }
+}
+
+pub struct Lints;
+
+impl Pass for Lints {
+    fn run(&self, ast: &mut Vec<Stmt>, diags: &mut Vec<Diagnostic>) {
+        for lint in lints::lint(ast) {
+            let (message, position) = match lint {
+                Lint::UnreachableCode(_) => ("unreachable code".to_string(), None),
+                Lint::AssignmentInCondition(name, position) => {
+                    (format!("assignment to {} in a condition, did you mean '=='?", name), Some(position))
+                }
+            };
+            diags.push(diagnostic(Severity::Warning, message, position));
+        }
+    }
+}
+
+// Constant folding and algebraic simplification; reports nothing.
+pub struct Optimize;
+
+impl Pass for Optimize {
+    fn run(&self, ast: &mut Vec<Stmt>, _diags: &mut Vec<Diagnostic>) {
+        *ast = fold::optimize(std::mem::take(ast));
+    }
+
+    fn rewrites(&self) -> bool {
+        true
+    }
+}
+
+fn diagnostic(severity: Severity, message: String, position: Option<Position>) -> Diagnostic {
+    let position = position.unwrap_or(Position { line: 0, column: 0 });
+    Diagnostic {
+        severity,
+        message,
+        line: position.line,
+        column: position.column,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(pipeline: &Pipeline, source: &str) -> (Vec<Stmt>, Vec<Diagnostic>) {
+        let mut ast = crate::parse_str(source).unwrap();
+        let diags = pipeline.run(&mut ast);
+        (ast, diags)
+    }
+
+    #[test]
+    fn resolver_and_type_checker_both_report() {
+        let pipeline = Pipeline::new().with_pass(Resolve).with_pass(TypeCheck);
+        let (_, diags) = run(&pipeline, "if (100) {\n    x = foo;\n}");
+        let messages: Vec<&str> = diags.iter().map(|diag| diag.message.as_str()).collect();
+        assert_eq!(messages.len(), 2, "{:?}", messages);
+        assert_eq!(messages[0], "undefined variable foo");
+        assert!(messages[1].contains("expected Bool"), "{}", messages[1]);
+        assert!(diags.iter().all(|diag| diag.severity == Severity::Error));
+    }
+
+    #[test]
+    fn type_errors_have_positions() {
+        let (_, diags) = run(&Pipeline::new().with_pass(TypeCheck), "x = 1;\ny = !x;");
+        assert_eq!(diags.len(), 1);
+        assert_eq!((diags[0].line, diags[0].column), (2, 6));
+        let (_, diags) = run(&Pipeline::new().with_pass(TypeCheck), "x = 1;\nif (2) {\n  y = x;\n}");
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].line, 3);
+    }
+
+    #[test]
+    fn errors_stop_the_pipeline_before_rewriting_passes() {
+        let pipeline = Pipeline::new().with_pass(Resolve).with_pass(Lints).with_pass(Optimize);
+        let (ast, diags) = run(&pipeline, "x = 1 + 2; y = z; if (a = true) { }");
+        assert!(diags.iter().any(|diag| diag.severity == Severity::Warning));
+        assert_eq!(ast, crate::parse_str("x = 1 + 2; y = z; if (a = true) { }").unwrap());
+        let (ast, diags) = run(&pipeline, "x = 1 + 2;");
+        assert!(diags.is_empty());
+        assert_eq!(ast, crate::parse_str("x = 3;").unwrap());
+    }
+}
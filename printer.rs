@@ -0,0 +1,259 @@
+use crate::parser::{binary_precedence, is_right_associative, Expr, NumType, Stmt, StringPart};
+
+const INDENT: &str = "    ";
+
+pub fn pretty_print(stmts: &[Stmt]) -> String {
+    let mut out = String::new();
+    for stmt in stmts {
+        print_stmt(stmt, 0, &mut out);
+    }
+    out
+}
+
+fn print_stmt(stmt: &Stmt, indent: usize, out: &mut String) {
+    out.push_str(&INDENT.repeat(indent));
+    match stmt {
+        Stmt::VarDecl(name, Some(value)) => out.push_str(&format!("let {} = {};", name, print_expr(value))),
+        Stmt::VarDecl(name, None) => out.push_str(&format!("let {};", name)),
+        Stmt::ConstDecl(name, value) => out.push_str(&format!("const {} = {};", name, print_expr(value))),
+        Stmt::Assignment(name, value) => out.push_str(&format!("{} = {};", name, print_expr(value))),
+        Stmt::CompoundAssignment(name, op, value) => out.push_str(&format!("{} {} {};", name, op, print_expr(value))),
+        Stmt::Increment(name, op) => out.push_str(&format!("{}{};", name, op)),
+        Stmt::FunctionDeclaration(name, params, body, pure) => {
+            let params: Vec<String> = params
+                .iter()
+                .map(|(param, default)| match default {
+                    Some(default) => format!("{} = {}", param, print_expr(default)),
+                    None => param.clone(),
+                })
+                .collect();
+            if *pure {
+                out.push_str("pure ");
+            }
+            out.push_str(&format!("function {}({}) ", name, params.join(", ")));
+            print_block(body, indent, out);
+        }
+        Stmt::If(condition, body, else_body) => {
+            // An `If` alone in the else body stays braced: `else if` would
+            // re-parse as an `IfChain`.
+            out.push_str(&format!("if ({}) ", print_expr(condition)));
+            print_block(body, indent, out);
+            if let Some(else_body) = else_body {
+                out.push_str(" else ");
+                print_block(else_body, indent, out);
+            }
+        }
+        Stmt::IfChain(arms, else_body) => {
+            for (i, (condition, body)) in arms.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(" else ");
+                }
+                out.push_str(&format!("if ({}) ", print_expr(condition)));
+                print_block(body, indent, out);
+            }
+            if let Some(else_body) = else_body {
+                out.push_str(" else ");
+                print_block(else_body, indent, out);
+            }
+        }
+        Stmt::Switch(subject, cases, default) => {
+            out.push_str(&format!("switch ({}) {{\n", print_expr(subject)));
+            for (value, body) in cases {
+                out.push_str(&INDENT.repeat(indent + 1));
+                out.push_str(&format!("case {} ", print_expr(value)));
+                print_block(body, indent + 1, out);
+                out.push('\n');
+            }
+            if let Some(default) = default {
+                out.push_str(&INDENT.repeat(indent + 1));
+                out.push_str("default ");
+                print_block(default, indent + 1, out);
+                out.push('\n');
+            }
+            out.push_str(&INDENT.repeat(indent));
+            out.push('}');
+        }
+        Stmt::While(condition, body) => {
+            out.push_str(&format!("while ({}) ", print_expr(condition)));
+            print_block(body, indent, out);
+        }
+        Stmt::ForRange(name, range, body) => {
+            out.push_str(&format!("for ({} in {}) ", name, print_expr(range)));
+            print_block(body, indent, out);
+        }
+        Stmt::ForClassic(init, condition, step, body) => {
+            let step = print_clause(step);
+            out.push_str(&format!(
+                "for ({} {}; {}) ",
+                print_clause(init),
+                print_expr(condition),
+                step.trim_end_matches(';')
+            ));
+            print_block(body, indent, out);
+        }
+        Stmt::Block(body) => print_block(body, indent, out),
+        Stmt::Break => out.push_str("break;"),
+        Stmt::Continue => out.push_str("continue;"),
+        Stmt::Import(path) => out.push_str(&format!("import \"{}\";", escape_string(path))),
+        Stmt::Return(Some(value)) => out.push_str(&format!("return {};", print_expr(value))),
+        Stmt::Return(None) => out.push_str("return;"),
+        Stmt::EventHandler(event, body) => {
+            out.push_str(&format!("on {} ", event));
+            print_block(body, indent, out);
+        }
+        Stmt::Expression(expr) => out.push_str(&format!("{};", print_expr(expr))),
+    }
+    out.push('\n');
+}
+
+// A simple statement on its own, without the indent and line break.
+fn print_clause(stmt: &Stmt) -> String {
+    let mut out = String::new();
+    print_stmt(stmt, 0, &mut out);
+    out.trim_end().to_string()
+}
+
+fn print_block(body: &[Stmt], indent: usize, out: &mut String) {
+    out.push_str("{\n");
+    for stmt in body {
+        print_stmt(stmt, indent + 1, out);
+    }
+    out.push_str(&INDENT.repeat(indent));
+    out.push('}');
+}
+
+pub fn print_expr(expr: &Expr) -> String {
+    match expr {
+        Expr::NumberLiteral(value, NumType::I32) => value.to_string(),
+        Expr::NumberLiteral(value, ty) => format!("{}{}", value, ty.suffix()),
+        Expr::FloatLiteral(value) => format!("{:?}", value),
+        Expr::BoolLiteral(value) => value.to_string(),
+        Expr::StringLiteral(value) => format!("\"{}\"", escape_string(value)),
+        Expr::Quantity(value, unit) => format!("{}{}", value, unit.suffix()),
+        Expr::Variable(name, _) => name.clone(),
+        Expr::Unary(op, operand) => match **operand {
+            Expr::Unary(..) | Expr::Binary(..) | Expr::Ternary(..) | Expr::Assign(..) => format!("{}({})", op, print_expr(operand)),
+            _ => format!("{}{}", op, print_expr(operand)),
+        },
+        Expr::Binary(left, op, right) => {
+            let precedence = binary_precedence(op).unwrap_or(0);
+            let right_assoc = is_right_associative(op);
+            format!(
+                "{} {} {}",
+                print_operand(left, |p| p < precedence || (right_assoc && p == precedence)),
+                op,
+                print_operand(right, |p| p < precedence || (!right_assoc && p == precedence))
+            )
+        }
+        Expr::Ternary(condition, then_branch, else_branch) => {
+            // The else branch is parsed as another conditional, so only an
+            // embedded assignment needs parentheses there.
+            let else_branch = match **else_branch {
+                Expr::Assign(..) => format!("({})", print_expr(else_branch)),
+                _ => print_expr(else_branch),
+            };
+            format!("{} ? {} : {}", print_operand(condition, |_| false), print_expr(then_branch), else_branch)
+        }
+        Expr::Assign(name, value, _) => format!("{} = {}", name, print_expr(value)),
+        Expr::Call(name, args) => {
+            let args: Vec<String> = args.iter().map(print_expr).collect();
+            format!("{}({})", name, args.join(", "))
+        }
+        Expr::ArrayLiteral(elements) => {
+            let elements: Vec<String> = elements.iter().map(print_expr).collect();
+            format!("[{}]", elements.join(", "))
+        }
+        Expr::Index(target, index) => match **target {
+            Expr::Unary(..) | Expr::Binary(..) | Expr::Ternary(..) | Expr::Assign(..) => {
+                format!("({})[{}]", print_expr(target), print_expr(index))
+            }
+            _ => format!("{}[{}]", print_expr(target), print_expr(index)),
+        },
+        Expr::Range(start, end) => format!("{}..{}", print_operand(start, |_| false), print_operand(end, |_| false)),
+        Expr::Interpolation(parts) => {
+            let parts: Vec<String> = parts
+                .iter()
+                .map(|part| match part {
+                    StringPart::Literal(text) => escape_string(text),
+                    StringPart::Expr(expr) => format!("${{{}}}", print_expr(expr)),
+                })
+                .collect();
+            format!("\"{}\"", parts.concat())
+        }
+    }
+}
+
+// The inverse of the lexer's escape decoding, so printed strings re-lex to
+// the same value.
+fn escape_string(value: &str) -> String {
+    let mut out = String::new();
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '$' if chars.peek() == Some(&'{') => out.push_str("\\$"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+// Wraps a binary operand in parentheses when re-parsing it without them
+// would bind differently.
+fn print_operand(expr: &Expr, needs_parens: impl Fn(u8) -> bool) -> String {
+    match expr {
+        Expr::Binary(_, op, _) if needs_parens(binary_precedence(op).unwrap_or(0)) => format!("({})", print_expr(expr)),
+        Expr::Ternary(..) | Expr::Assign(..) => format!("({})", print_expr(expr)),
+        _ => print_expr(expr),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reprint(source: &str) -> String {
+        pretty_print(&crate::parse_str(source).unwrap())
+    }
+
+    fn assert_round_trips(source: &str) {
+        let printed = reprint(source);
+        assert_eq!(crate::parse_str(&printed).unwrap(), crate::parse_str(source).unwrap(), "{}", printed);
+        assert_eq!(reprint(&printed), printed);
+    }
+
+    #[test]
+    fn readme_prints_canonically() {
+        let source = "
+        function startEngine() {
+            speed = 100;
+            if (speed > 60) {
+                applyBrakes();
+            }
+        }
+    ";
+        assert_eq!(
+            reprint(source),
+            "function startEngine() {\n    speed = 100;\n    if (speed > 60) {\n        applyBrakes();\n    }\n}\n"
+        );
+        assert_round_trips(source);
+    }
+
+    #[test]
+    fn nested_blocks_round_trip() {
+        assert_round_trips(
+            "function f(a, b) { let x = (a - b) - (1 - 2) * -(3 + 4); x += 2.0; if (a) { return; } else if (!b) { while (a && (b || c)) { g(1, \"s\"); } } else { return x / (y * z); } }",
+        );
+    }
+
+    #[test]
+    fn if_chain_prints_as_else_if() {
+        assert_eq!(
+            reprint("if (a) { x = 1; } else if (b) { x = 2; } else unless (c) { x = 3; } else { x = 4; }"),
+            "if (a) {\n    x = 1;\n} else if (b) {\n    x = 2;\n} else if (!c) {\n    x = 3;\n} else {\n    x = 4;\n}\n"
+        );
+    }
+}
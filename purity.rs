@@ -0,0 +1,80 @@
+use crate::builtins;
+use crate::parser::{Expr, Stmt};
+use crate::visit::{walk_stmt, walk_stmts, Visitor};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PurityViolation {
+    // The `pure` function and the side-effecting builtin it calls.
+    pub function: String,
+    pub call: String,
+}
+
+// Checks that no `pure function` calls an actuator or other side-effecting
+// builtin. A function declared inside another is checked against its own
+// annotation only.
+pub fn check_purity(stmts: &[Stmt]) -> Vec<PurityViolation> {
+    let mut checker = PurityChecker {
+        function: None,
+        violations: Vec::new(),
+    };
+    walk_stmts(&mut checker, stmts);
+    checker.violations
+}
+
+struct PurityChecker {
+    // The enclosing function, if it is `pure`.
+    function: Option<String>,
+    violations: Vec<PurityViolation>,
+}
+
+impl Visitor for PurityChecker {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        if let Stmt::FunctionDeclaration(name, _, _, pure) = stmt {
+            let enclosing = std::mem::replace(&mut self.function, pure.then(|| name.clone()));
+            walk_stmt(self, stmt);
+            self.function = enclosing;
+        } else {
+            walk_stmt(self, stmt);
+        }
+    }
+
+    fn visit_call(&mut self, name: &str, args: &[Expr]) {
+        if let Some(function) = &self.function {
+            if builtins::has_side_effects(name) {
+                self.violations.push(PurityViolation {
+                    function: function.clone(),
+                    call: name.to_string(),
+                });
+            }
+        }
+        for arg in args {
+            self.visit_expr(arg);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(source: &str) -> Vec<PurityViolation> {
+        check_purity(&crate::parse_str(source).unwrap())
+    }
+
+    #[test]
+    fn pure_arithmetic_passes() {
+        assert!(check("pure function area(w, h) { return w * h + 1; } x = area(2, 3);").is_empty());
+        assert!(check("pure function f() { function g() { honk(); } return readSpeed(); }").is_empty());
+    }
+
+    #[test]
+    fn actuator_call_in_a_pure_function_is_a_violation() {
+        assert_eq!(
+            check("pure function stop() { if (true) { applyBrakes(); } log(\"x\"); } function go() { setSpeed(1); }"),
+            vec![
+                PurityViolation { function: "stop".into(), call: "applyBrakes".into() },
+                PurityViolation { function: "stop".into(), call: "log".into() },
+            ]
+        );
+    }
+}
@@ -0,0 +1,316 @@
+use crate::lexer::Position;
+use crate::parser::{Expr, Stmt, StringPart};
+use crate::visit::{walk_expr, walk_stmts, Visitor};
+
+// Renames the outermost variable called `old` to `new` along with every
+// reference to it, returning how many names changed. That is the global
+// when the top level declares one, and otherwise each local or parameter
+// of that name not nested inside another. A `let`, `const`, parameter or
+// loop variable reusing the name inside the renamed binding's scope shadows
+// it; that binding and the references to it are left alone. Scopes are
+// those the interpreter creates: function and handler bodies, blocks and
+// `for` loops.
+pub fn rename_symbol(stmts: &mut [Stmt], old: &str, new: &str) -> usize {
+    let mut renamer = Renamer::new(stmts, old, new, Target::Outermost);
+    renamer.block(stmts);
+    renamer.count
+}
+
+// Renames whichever binding the variable or assignment at `at` refers to,
+// in whatever scope it was declared, again leaving shadowing declarations
+// of the same name alone. Returns 0 when no variable sits at `at`.
+pub fn rename_symbol_at(stmts: &mut [Stmt], at: Position, new: &str) -> usize {
+    let mut finder = NameFinder { at, name: None };
+    walk_stmts(&mut finder, stmts);
+    let Some(old) = finder.name else {
+        return 0;
+    };
+    let mut locator = Renamer::new(stmts, &old, new, Target::Locate(at));
+    locator.block(stmts);
+    let Some(scope) = locator.located else {
+        return 0;
+    };
+    let mut renamer = Renamer::new(stmts, &old, new, Target::Scope(scope));
+    renamer.block(stmts);
+    renamer.count
+}
+
+struct NameFinder {
+    at: Position,
+    name: Option<String>,
+}
+
+impl Visitor for NameFinder {
+    fn visit_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Variable(name, position) | Expr::Assign(name, _, position) if *position == self.at => {
+                self.name = Some(name.clone());
+            }
+            _ => {}
+        }
+        walk_expr(self, expr);
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Target {
+    Outermost,
+    // The scope, numbered in the order scopes are entered with the top
+    // level as 0, whose binding is renamed.
+    Scope(usize),
+    // Renames nothing; records the scope of the binding referenced at the
+    // position.
+    Locate(Position),
+}
+
+struct Scope {
+    id: usize,
+    // Whether the scope declares `old` at the point reached.
+    declares: bool,
+}
+
+struct Renamer<'a> {
+    old: &'a str,
+    new: &'a str,
+    target: Target,
+    scopes: Vec<Scope>,
+    next_id: usize,
+    located: Option<usize>,
+    count: usize,
+}
+
+impl<'a> Renamer<'a> {
+    fn new(stmts: &[Stmt], old: &'a str, new: &'a str, target: Target) -> Self {
+        Renamer {
+            old,
+            new,
+            target,
+            scopes: vec![Scope {
+                id: 0,
+                declares: declares_global(stmts, old),
+            }],
+            next_id: 1,
+            located: None,
+            count: 0,
+        }
+    }
+
+    // An occurrence of a name, belonging to the innermost scope declaring
+    // it so far; a name no scope declares is a global.
+    fn reference(&mut self, name: &mut String, position: Option<Position>) {
+        if name != self.old {
+            return;
+        }
+        let binding = self.scopes.iter().rev().find(|scope| scope.declares).map_or(0, |scope| scope.id);
+        let selected = match self.target {
+            Target::Outermost => self.scopes.iter().filter(|scope| scope.declares).count() <= 1,
+            Target::Scope(id) => binding == id,
+            Target::Locate(at) => {
+                if position == Some(at) {
+                    self.located = Some(binding);
+                }
+                false
+            }
+        };
+        if selected {
+            *name = self.new.to_string();
+            self.count += 1;
+        }
+    }
+
+    fn declare(&mut self, name: &mut String) {
+        if name == self.old {
+            if let Some(scope) = self.scopes.last_mut() {
+                scope.declares = true;
+            }
+        }
+        self.reference(name, None);
+    }
+
+    fn scoped(&mut self, visit: impl FnOnce(&mut Self)) {
+        self.scopes.push(Scope {
+            id: self.next_id,
+            declares: false,
+        });
+        self.next_id += 1;
+        visit(self);
+        self.scopes.pop();
+    }
+
+    fn block(&mut self, stmts: &mut [Stmt]) {
+        for stmt in stmts {
+            self.stmt(stmt);
+        }
+    }
+
+    fn stmt(&mut self, stmt: &mut Stmt) {
+        match stmt {
+            Stmt::VarDecl(name, value) => {
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+                self.declare(name);
+            }
+            Stmt::ConstDecl(name, value) => {
+                self.expr(value);
+                self.declare(name);
+            }
+            Stmt::Assignment(name, value) | Stmt::CompoundAssignment(name, _, value) => {
+                self.expr(value);
+                self.reference(name, None);
+            }
+            Stmt::Increment(name, _) => self.reference(name, None),
+            // Defaults run in the callee's scope once earlier parameters are
+            // bound, so each sees the parameters before it.
+            Stmt::FunctionDeclaration(_, params, body, _) => self.scoped(|renamer| {
+                for (param, default) in params.iter_mut() {
+                    if let Some(default) = default {
+                        renamer.expr(default);
+                    }
+                    renamer.declare(param);
+                }
+                renamer.block(body);
+            }),
+            Stmt::EventHandler(_, body) | Stmt::Block(body) => self.scoped(|renamer| renamer.block(body)),
+            Stmt::If(condition, body, else_body) => {
+                self.expr(condition);
+                self.block(body);
+                if let Some(else_body) = else_body {
+                    self.block(else_body);
+                }
+            }
+            Stmt::IfChain(arms, else_body) => self.arms(arms, else_body),
+            Stmt::Switch(subject, cases, default) => {
+                self.expr(subject);
+                self.arms(cases, default);
+            }
+            Stmt::While(condition, body) => {
+                self.expr(condition);
+                self.block(body);
+            }
+            Stmt::ForRange(name, range, body) => {
+                self.expr(range);
+                self.scoped(|renamer| {
+                    renamer.declare(name);
+                    renamer.block(body);
+                });
+            }
+            Stmt::ForClassic(init, condition, step, body) => self.scoped(|renamer| {
+                renamer.stmt(init);
+                renamer.expr(condition);
+                renamer.stmt(step);
+                renamer.block(body);
+            }),
+            Stmt::Return(value) => {
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+            }
+            Stmt::Expression(expr) => self.expr(expr),
+            Stmt::Break | Stmt::Continue | Stmt::Import(_) => {}
+        }
+    }
+
+    fn arms(&mut self, arms: &mut [(Expr, Vec<Stmt>)], else_body: &mut Option<Vec<Stmt>>) {
+        for (value, body) in arms {
+            self.expr(value);
+            self.block(body);
+        }
+        if let Some(else_body) = else_body {
+            self.block(else_body);
+        }
+    }
+
+    fn expr(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Variable(name, position) => self.reference(name, Some(*position)),
+            Expr::Assign(name, value, position) => {
+                self.expr(value);
+                self.reference(name, Some(*position));
+            }
+            Expr::Unary(_, operand) => self.expr(operand),
+            Expr::Binary(left, _, right) | Expr::Index(left, right) | Expr::Range(left, right) => {
+                self.expr(left);
+                self.expr(right);
+            }
+            Expr::Ternary(condition, then_branch, else_branch) => {
+                self.expr(condition);
+                self.expr(then_branch);
+                self.expr(else_branch);
+            }
+            Expr::Call(_, args) | Expr::ArrayLiteral(args) => {
+                for arg in args {
+                    self.expr(arg);
+                }
+            }
+            Expr::Interpolation(parts) => {
+                for part in parts {
+                    if let StringPart::Expr(expr) = part {
+                        self.expr(expr);
+                    }
+                }
+            }
+            Expr::NumberLiteral(..)
+            | Expr::FloatLiteral(_)
+            | Expr::BoolLiteral(_)
+            | Expr::StringLiteral(_)
+            | Expr::Quantity(..) => {}
+        }
+    }
+}
+
+// Whether the top level binds `name` as a global, including from the
+// bodies of `if`, `switch` and `while`, which have no scope of their own.
+fn declares_global(stmts: &[Stmt], name: &str) -> bool {
+    stmts.iter().any(|stmt| match stmt {
+        Stmt::VarDecl(declared, _)
+        | Stmt::ConstDecl(declared, _)
+        | Stmt::Assignment(declared, _)
+        | Stmt::CompoundAssignment(declared, _, _)
+        | Stmt::Increment(declared, _) => declared == name,
+        Stmt::Expression(Expr::Assign(declared, _, _)) => declared == name,
+        Stmt::If(_, body, else_body) => {
+            declares_global(body, name) || else_body.as_deref().is_some_and(|body| declares_global(body, name))
+        }
+        Stmt::IfChain(arms, else_body) | Stmt::Switch(_, arms, else_body) => {
+            arms.iter().any(|(_, body)| declares_global(body, name))
+                || else_body.as_deref().is_some_and(|body| declares_global(body, name))
+        }
+        Stmt::While(_, body) => declares_global(body, name),
+        _ => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn renamed(source: &str, old: &str, new: &str) -> (usize, Vec<Stmt>) {
+        let mut stmts = crate::parse_str(source).unwrap();
+        let count = rename_symbol(&mut stmts, old, new);
+        (count, stmts)
+    }
+
+    #[test]
+    fn renames_every_occurrence() {
+        let (count, stmts) = renamed("let speed = 1; speed += 2; x = speed * 2; if (speed > 1) { speed = \"${speed}\"; }", "speed", "velocity");
+        assert_eq!(count, 6);
+        assert_eq!(stmts, crate::parse_str("let velocity = 1; velocity += 2; x = velocity * 2; if (velocity > 1) { velocity = \"${velocity}\"; }").unwrap());
+        assert_eq!(renamed("x = 1;", "y", "z").0, 0);
+    }
+
+    #[test]
+    fn shadowing_names_are_left_alone() {
+        let (count, stmts) = renamed("speed = 1; function f(speed) { return speed; } { let speed = 2; g(speed); } r = speed;", "speed", "v");
+        assert_eq!(count, 2);
+        assert_eq!(stmts, crate::parse_str("v = 1; function f(speed) { return speed; } { let speed = 2; g(speed); } r = v;").unwrap());
+    }
+
+    #[test]
+    fn renames_the_symbol_at_a_position() {
+        let source = "speed = 1;\nfunction f(speed) {\n  x = speed;\n  return speed;\n}\nr = speed;";
+        let mut stmts = crate::parse_str(source).unwrap();
+        assert_eq!(rename_symbol_at(&mut stmts, Position { line: 3, column: 7 }, "v"), 3);
+        assert_eq!(stmts, crate::parse_str("speed = 1; function f(v) { x = v; return v; } r = speed;").unwrap());
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+
+use crate::lexer::Position;
+use crate::parser::{Expr, Stmt};
+use crate::visit::{walk_expr, walk_stmt, walk_stmts, Visitor};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResolveError {
+    UndefinedVariable(String, Position),
+    CannotReassignConst(String),
+    // Only reported by `resolve_strict`.
+    UndeclaredAssignment(String),
+    BreakOutsideLoop,
+    ContinueOutsideLoop,
+}
+
+pub fn resolve(stmts: &[Stmt]) -> Vec<ResolveError> {
+    run_resolver(stmts, false)
+}
+
+// Like `resolve`, but assigning a name never declared with `let` or `const`
+// is an error instead of declaring a global.
+pub fn resolve_strict(stmts: &[Stmt]) -> Vec<ResolveError> {
+    run_resolver(stmts, true)
+}
+
+fn run_resolver(stmts: &[Stmt], strict: bool) -> Vec<ResolveError> {
+    let mut resolver = Resolver {
+        scopes: vec![HashMap::new()],
+        loop_depth: 0,
+        strict,
+        errors: Vec::new(),
+    };
+    walk_stmts(&mut resolver, stmts);
+    resolver.errors
+}
+
+// One scope per function body and bare block plus the top level;
+// `if`/`while` bodies declare into the enclosing scope, and assigning an
+// unknown name declares a global.
+struct Resolver {
+    scopes: Vec<HashMap<String, Binding>>,
+    // `while` loops enclosing the current statement; reset inside function
+    // and handler bodies, which a `break` cannot escape.
+    loop_depth: usize,
+    strict: bool,
+    errors: Vec<ResolveError>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Binding {
+    Variable,
+    Const,
+}
+
+impl Resolver {
+    fn declare(&mut self, name: &str, binding: Binding) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), binding);
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<Binding> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name).copied())
+    }
+
+    fn is_declared(&self, name: &str) -> bool {
+        self.lookup(name).is_some()
+    }
+
+    fn walk_body(&mut self, body: &[Stmt]) {
+        let loop_depth = std::mem::replace(&mut self.loop_depth, 0);
+        walk_stmts(self, body);
+        self.loop_depth = loop_depth;
+    }
+
+    fn assign(&mut self, name: &str) {
+        match self.lookup(name) {
+            Some(Binding::Const) => self.errors.push(ResolveError::CannotReassignConst(name.to_string())),
+            Some(Binding::Variable) => {}
+            None => {
+                if self.strict {
+                    self.errors.push(ResolveError::UndeclaredAssignment(name.to_string()));
+                }
+                // Declared even when strict, so later reads are not reported too.
+                self.scopes[0].insert(name.to_string(), Binding::Variable);
+            }
+        }
+    }
+}
+
+impl Visitor for Resolver {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::VarDecl(name, value) => {
+                if let Some(value) = value {
+                    self.visit_expr(value);
+                }
+                self.declare(name, Binding::Variable);
+            }
+            Stmt::ConstDecl(name, value) => {
+                self.visit_expr(value);
+                self.declare(name, Binding::Const);
+            }
+            Stmt::Assignment(name, value) => {
+                self.visit_expr(value);
+                self.assign(name);
+            }
+            Stmt::Increment(name, _) => self.assign(name),
+            Stmt::CompoundAssignment(name, _, value) => {
+                self.visit_expr(value);
+                self.assign(name);
+            }
+            Stmt::FunctionDeclaration(_, params, body, _) => {
+                self.scopes.push(params.iter().map(|(param, _)| (param.clone(), Binding::Variable)).collect());
+                for default in params.iter().filter_map(|(_, default)| default.as_ref()) {
+                    self.visit_expr(default);
+                }
+                self.walk_body(body);
+                self.scopes.pop();
+            }
+            Stmt::EventHandler(_, body) => self.walk_body(body),
+            Stmt::While(condition, body) => {
+                self.visit_expr(condition);
+                self.loop_depth += 1;
+                walk_stmts(self, body);
+                self.loop_depth -= 1;
+            }
+            Stmt::ForRange(name, range, body) => {
+                self.visit_expr(range);
+                self.scopes.push(HashMap::from([(name.clone(), Binding::Variable)]));
+                self.loop_depth += 1;
+                walk_stmts(self, body);
+                self.loop_depth -= 1;
+                self.scopes.pop();
+            }
+            Stmt::ForClassic(init, condition, step, body) => {
+                self.scopes.push(HashMap::new());
+                self.visit_stmt(init);
+                self.visit_expr(condition);
+                self.visit_stmt(step);
+                self.loop_depth += 1;
+                walk_stmts(self, body);
+                self.loop_depth -= 1;
+                self.scopes.pop();
+            }
+            Stmt::Block(body) => {
+                self.scopes.push(HashMap::new());
+                walk_stmts(self, body);
+                self.scopes.pop();
+            }
+            Stmt::Break if self.loop_depth == 0 => self.errors.push(ResolveError::BreakOutsideLoop),
+            Stmt::Continue if self.loop_depth == 0 => self.errors.push(ResolveError::ContinueOutsideLoop),
+            _ => walk_stmt(self, stmt),
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Variable(name, position) if !self.is_declared(name) => {
+                self.errors.push(ResolveError::UndefinedVariable(name.clone(), *position));
+            }
+            Expr::Assign(name, value, _) => {
+                self.visit_expr(value);
+                self.assign(name);
+            }
+            _ => walk_expr(self, expr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(source: &str) -> Vec<ResolveError> {
+        resolve(&crate::parse_str(source).unwrap())
+    }
+
+    #[test]
+    fn parameters_and_assigned_names_resolve() {
+        assert!(check("function f(a) { if (a > 1) { b = a; } while (b) { b = false; } return b; }").is_empty());
+        assert!(check("speed = 1; function f() { return speed; }").is_empty());
+    }
+
+    #[test]
+    fn undeclared_read_names_the_variable_and_position() {
+        let errors = check("function f(a) {\n  return a + foo;\n}");
+        assert_eq!(errors, vec![ResolveError::UndefinedVariable("foo".into(), Position { line: 2, column: 14 })]);
+    }
+
+    #[test]
+    fn reassigning_a_const_is_rejected() {
+        assert_eq!(check("const MAX_SPEED = 130; MAX_SPEED = 1;"), vec![ResolveError::CannotReassignConst("MAX_SPEED".into())]);
+        assert_eq!(check("const M = 130; function f() { M += 1; }"), vec![ResolveError::CannotReassignConst("M".into())]);
+        assert!(check("const M = 1; x = M; function f(M) { M = 2; }").is_empty());
+    }
+
+    #[test]
+    fn break_and_continue_need_an_enclosing_loop() {
+        assert_eq!(check("break;"), vec![ResolveError::BreakOutsideLoop]);
+        assert_eq!(check("while (true) { function f() { continue; } }"), vec![ResolveError::ContinueOutsideLoop]);
+        assert!(check("while (true) { if (true) { break; } }").is_empty());
+    }
+
+    #[test]
+    fn strict_mode_rejects_undeclared_assignment() {
+        let ast = crate::parse_str("function startEngine() { speed = 100; }").unwrap();
+        assert!(resolve(&ast).is_empty());
+        assert_eq!(resolve_strict(&ast), vec![ResolveError::UndeclaredAssignment("speed".into())]);
+        let ast = crate::parse_str("let speed = 0; function f() { let x = 1; x += 1; speed = 100; } f();").unwrap();
+        assert!(resolve_strict(&ast).is_empty());
+    }
+
+    #[test]
+    fn block_locals_are_undefined_after_the_block() {
+        assert!(matches!(&check("{ let t = 1; } x = t;")[..], [ResolveError::UndefinedVariable(name, _)] if name == "t"));
+        assert!(check("let s = 0; while (s < 3) { { s += 1; break; } }").is_empty());
+    }
+}
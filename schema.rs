@@ -0,0 +1,44 @@
+use schemars::schema::RootSchema;
+use schemars::schema_for;
+
+use crate::parser::Stmt;
+
+// The JSON Schema of a serialized program, i.e. of `Vec<Stmt>` as written
+// by the `serde` feature, for tooling outside Rust that reads the AST.
+pub fn schema() -> RootSchema {
+    schema_for!(Vec<Stmt>)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonschema::JSONSchema;
+
+    fn validator() -> JSONSchema {
+        JSONSchema::compile(&serde_json::to_value(schema()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn readme_ast_validates() {
+        let source = "
+        function startEngine() {
+            speed = 100;
+            if (speed > 60) {
+                applyBrakes();
+            }
+        }
+    ";
+        let ast = serde_json::to_value(crate::parse_str(source).unwrap()).unwrap();
+        let validator = validator();
+        let errors: Vec<_> = match validator.validate(&ast) {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors.map(|err| format!("{} at {}", err, err.instance_path)).collect(),
+        };
+        assert!(errors.is_empty(), "{}", errors.join("\n"));
+    }
+
+    #[test]
+    fn unknown_node_kind_is_invalid() {
+        assert!(!validator().is_valid(&serde_json::json!([{ "kind": "Bogus" }])));
+    }
+}
@@ -0,0 +1,59 @@
+use std::process::{Command, Output};
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_vac_parser"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .expect("the binary runs")
+}
+
+fn stdout(output: &Output) -> &str {
+    std::str::from_utf8(&output.stdout).unwrap()
+}
+
+fn stderr(output: &Output) -> &str {
+    std::str::from_utf8(&output.stderr).unwrap()
+}
+
+#[test]
+fn prints_a_valid_script_back_as_source() {
+    let output = run(&["tests/fixtures/start_engine.vac"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "function startEngine() {\n    speed = 100;\n    if (speed > 60) {\n        applyBrakes();\n    }\n}\non ignition {\n    startEngine();\n}\n"
+    );
+}
+
+#[test]
+fn dumps_the_ast_with_a_flag() {
+    let output = run(&["--ast", "tests/fixtures/start_engine.vac"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains("FunctionDeclaration("), "{}", stdout(&output));
+}
+
+#[test]
+fn dumps_tokens_with_a_flag() {
+    let output = run(&["--tokens", "tests/fixtures/start_engine.vac"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).lines().any(|line| line.starts_with("2:10 ")), "{}", stdout(&output));
+}
+
+#[test]
+fn reports_a_parse_error_with_a_snippet() {
+    let output = run(&["tests/fixtures/unclosed_if.vac"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).is_empty());
+    let report = stderr(&output);
+    assert!(report.starts_with("error: expected ')'"), "{}", report);
+    assert!(report.contains("3 |     if (speed > 60 {"), "{}", report);
+}
+
+#[test]
+fn reports_an_unreadable_file() {
+    let output = run(&["tests/fixtures/missing.vac"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).starts_with("error: cannot read"), "{}", stderr(&output));
+    assert_eq!(run(&[]).status.code(), Some(2));
+}
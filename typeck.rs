@@ -0,0 +1,426 @@
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use crate::builtins;
+use crate::lexer::Position;
+use crate::parser::{Dimension, Expr, NumType, Stmt, StringPart};
+use crate::printer::print_expr;
+use crate::visit::{walk_expr, walk_stmt, Visitor};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Type {
+    Int,
+    Float,
+    Bool,
+    Str,
+    Quantity(Dimension),
+    // Parameters, call results and uninitialized variables: compatible with
+    // every type since nothing is annotated.
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq, Error)]
+#[error("{node}: expected {expected}, found {found:?}")]
+pub struct TypeError {
+    pub node: String,
+    pub expected: String,
+    pub found: Type,
+    // As close as the AST records: the first variable or assignment in the
+    // offending expression, or else in the statement containing it.
+    pub position: Option<Position>,
+}
+
+pub fn check(stmts: &[Stmt]) -> Vec<TypeError> {
+    let mut checker = TypeChecker {
+        scopes: vec![HashMap::new()],
+        errors: Vec::new(),
+        statement: None,
+    };
+    checker.check_block(stmts);
+    checker.errors
+}
+
+struct TypeChecker {
+    scopes: Vec<HashMap<String, Type>>,
+    errors: Vec<TypeError>,
+    // The position of the statement being checked.
+    statement: Option<Position>,
+}
+
+impl TypeChecker {
+    fn check_block(&mut self, stmts: &[Stmt]) {
+        for stmt in stmts {
+            self.check_stmt(stmt);
+        }
+    }
+
+    fn check_stmt(&mut self, stmt: &Stmt) {
+        let outer = self.statement;
+        let mut first = FirstPosition(None);
+        walk_stmt(&mut first, stmt);
+        self.statement = first.0.or(outer);
+        self.check_stmt_kind(stmt);
+        self.statement = outer;
+    }
+
+    fn check_stmt_kind(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::VarDecl(name, value) => {
+                let ty = match value {
+                    Some(value) => self.infer(value),
+                    None => Type::Unknown,
+                };
+                self.declare(name, ty);
+            }
+            Stmt::ConstDecl(name, value) | Stmt::Assignment(name, value) => {
+                let ty = self.infer(value);
+                self.declare(name, ty);
+            }
+            Stmt::CompoundAssignment(_, _, value) => {
+                self.expect_numeric(value);
+            }
+            Stmt::FunctionDeclaration(_, params, body, _) => {
+                self.scopes.push(params.iter().map(|(param, _)| (param.clone(), Type::Unknown)).collect());
+                for default in params.iter().filter_map(|(_, default)| default.as_ref()) {
+                    self.infer(default);
+                }
+                self.check_block(body);
+                self.scopes.pop();
+            }
+            Stmt::If(condition, body, else_body) => {
+                self.expect(condition, Type::Bool);
+                self.check_block(body);
+                if let Some(else_body) = else_body {
+                    self.check_block(else_body);
+                }
+            }
+            Stmt::IfChain(arms, else_body) => {
+                for (condition, body) in arms {
+                    self.expect(condition, Type::Bool);
+                    self.check_block(body);
+                }
+                if let Some(else_body) = else_body {
+                    self.check_block(else_body);
+                }
+            }
+            Stmt::Switch(subject, cases, default) => {
+                let subject_ty = self.infer(subject);
+                for (value, body) in cases {
+                    self.expect(value, subject_ty);
+                    self.check_block(body);
+                }
+                if let Some(default) = default {
+                    self.check_block(default);
+                }
+            }
+            Stmt::While(condition, body) => {
+                self.expect(condition, Type::Bool);
+                self.check_block(body);
+            }
+            Stmt::ForRange(name, range, body) => {
+                self.infer(range);
+                self.scopes.push(HashMap::from([(name.clone(), Type::Int)]));
+                self.check_block(body);
+                self.scopes.pop();
+            }
+            Stmt::ForClassic(init, condition, step, body) => {
+                self.scopes.push(HashMap::new());
+                self.check_stmt(init);
+                self.expect(condition, Type::Bool);
+                self.check_stmt(step);
+                self.check_block(body);
+                self.scopes.pop();
+            }
+            Stmt::Block(body) => {
+                self.scopes.push(HashMap::new());
+                self.check_block(body);
+                self.scopes.pop();
+            }
+            Stmt::Break | Stmt::Continue | Stmt::Increment(..) | Stmt::Import(_) => {}
+            Stmt::Return(value) => {
+                if let Some(value) = value {
+                    self.infer(value);
+                }
+            }
+            Stmt::EventHandler(_, body) => self.check_block(body),
+            Stmt::Expression(expr) => {
+                self.infer(expr);
+            }
+        }
+    }
+
+    fn infer(&mut self, expr: &Expr) -> Type {
+        match expr {
+            Expr::NumberLiteral(value, ty) => self.check_literal(expr, *value, *ty),
+            Expr::FloatLiteral(_) => Type::Float,
+            Expr::BoolLiteral(_) => Type::Bool,
+            Expr::StringLiteral(_) => Type::Str,
+            Expr::Quantity(_, unit) => Type::Quantity(unit.dimension()),
+            Expr::Variable(name, _) => self.lookup(name),
+            Expr::Unary(op, operand) if op == "!" => self.expect(operand, Type::Bool),
+            // The sign belongs to the literal, so `-128i8` is within range.
+            // The sign belongs to a literal, so `-128i8` is within range.
+            Expr::Unary(_, operand) => match **operand {
+                Expr::NumberLiteral(value, ty) => self.check_literal(expr, -value, ty),
+                _ => self.expect_numeric(operand),
+            },
+            Expr::Binary(left, op, right) => match op.as_str() {
+                "&&" | "||" => {
+                    self.expect(left, Type::Bool);
+                    self.expect(right, Type::Bool);
+                    Type::Bool
+                }
+                "==" | "!=" => {
+                    let left_ty = self.infer(left);
+                    let right_ty = self.infer(right);
+                    if !(compatible(left_ty, right_ty) || is_numeric(left_ty) && is_numeric(right_ty)) {
+                        self.error(right, &format!("{:?}", left_ty), right_ty);
+                    }
+                    Type::Bool
+                }
+                "<" | ">" | "<=" | ">=" => {
+                    let left_ty = self.expect_numeric(left);
+                    let right_ty = self.expect_numeric(right);
+                    self.check_dimensions(right, left_ty, right_ty);
+                    Type::Bool
+                }
+                _ => {
+                    let left_ty = self.expect_numeric(left);
+                    let right_ty = self.expect_numeric(right);
+                    match (op.as_str(), left_ty, right_ty) {
+                        (_, Type::Int, Type::Int) => Type::Int,
+                        ("+" | "-", Type::Quantity(_), _) | ("+" | "-", _, Type::Quantity(_)) => {
+                            self.check_dimensions(right, left_ty, right_ty);
+                            if left_ty == Type::Unknown { right_ty } else { left_ty }
+                        }
+                        ("*" | "/", Type::Quantity(_), Type::Int | Type::Float) => left_ty,
+                        ("*", Type::Int | Type::Float, Type::Quantity(_)) => right_ty,
+                        (_, Type::Quantity(_), _) | (_, _, Type::Quantity(_)) => Type::Unknown,
+                        (_, Type::Float, _) | (_, _, Type::Float) => Type::Float,
+                        _ => Type::Unknown,
+                    }
+                }
+            },
+            Expr::Ternary(condition, then_branch, else_branch) => {
+                self.expect(condition, Type::Bool);
+                let then_ty = self.infer(then_branch);
+                let else_ty = self.infer(else_branch);
+                self.unify(else_branch, then_ty, else_ty)
+            }
+            Expr::Assign(name, value, _) => {
+                let ty = self.infer(value);
+                self.declare(name, ty);
+                ty
+            }
+            Expr::Call(name, args) => {
+                for arg in args {
+                    self.infer(arg);
+                }
+                builtins::return_type(name).unwrap_or(Type::Unknown)
+            }
+            Expr::ArrayLiteral(args) => {
+                for arg in args {
+                    self.infer(arg);
+                }
+                Type::Unknown
+            }
+            Expr::Index(target, index) => {
+                self.infer(target);
+                self.expect(index, Type::Int);
+                Type::Unknown
+            }
+            Expr::Range(start, end) => {
+                self.expect(start, Type::Int);
+                self.expect(end, Type::Int);
+                Type::Unknown
+            }
+            // Any value can be interpolated.
+            Expr::Interpolation(parts) => {
+                for part in parts {
+                    if let StringPart::Expr(expr) = part {
+                        self.infer(expr);
+                    }
+                }
+                Type::Str
+            }
+        }
+    }
+
+    fn check_literal(&mut self, expr: &Expr, value: i64, ty: NumType) -> Type {
+        if !ty.contains(value) {
+            self.error(expr, &format!("a value within {}", ty.suffix()), Type::Int);
+        }
+        Type::Int
+    }
+
+    fn expect(&mut self, expr: &Expr, expected: Type) -> Type {
+        let found = self.infer(expr);
+        if !compatible(found, expected) {
+            self.error(expr, &format!("{:?}", expected), found);
+        }
+        expected
+    }
+
+    fn expect_numeric(&mut self, expr: &Expr) -> Type {
+        let found = self.infer(expr);
+        if !is_numeric(found) {
+            self.error(expr, "numeric", found);
+        }
+        found
+    }
+
+    // Quantities only combine with quantities of the same dimension; mixing
+    // in a plain number would silently drop the unit.
+    fn check_dimensions(&mut self, right: &Expr, left_ty: Type, right_ty: Type) {
+        let mismatch = match (left_ty, right_ty) {
+            (Type::Quantity(a), Type::Quantity(b)) => a != b,
+            (Type::Quantity(_), Type::Int | Type::Float) | (Type::Int | Type::Float, Type::Quantity(_)) => true,
+            _ => false,
+        };
+        if mismatch {
+            self.error(right, &format!("{:?}", left_ty), right_ty);
+        }
+    }
+
+    // The one type both branches of a conditional can take: an `Int` widens
+    // to `Float`, anything else has to match exactly.
+    fn unify(&mut self, expr: &Expr, a: Type, b: Type) -> Type {
+        match (a, b) {
+            _ if a == b => a,
+            (Type::Unknown, _) | (_, Type::Unknown) => Type::Unknown,
+            (Type::Int, Type::Float) | (Type::Float, Type::Int) => Type::Float,
+            _ => {
+                self.error(expr, &format!("{:?}", a), b);
+                Type::Unknown
+            }
+        }
+    }
+
+    fn error(&mut self, expr: &Expr, expected: &str, found: Type) {
+        let mut first = FirstPosition(None);
+        first.visit_expr(expr);
+        self.errors.push(TypeError {
+            node: print_expr(expr),
+            expected: expected.to_string(),
+            found,
+            position: first.0.or(self.statement),
+        });
+    }
+
+    fn declare(&mut self, name: &str, ty: Type) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), ty);
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Type {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name).copied())
+            .unwrap_or(Type::Unknown)
+    }
+}
+
+struct FirstPosition(Option<Position>);
+
+impl Visitor for FirstPosition {
+    fn visit_expr(&mut self, expr: &Expr) {
+        if self.0.is_some() {
+            return;
+        }
+        match expr {
+            Expr::Variable(_, position) | Expr::Assign(_, _, position) => self.0 = Some(*position),
+            _ => walk_expr(self, expr),
+        }
+    }
+}
+
+fn compatible(a: Type, b: Type) -> bool {
+    a == b || a == Type::Unknown || b == Type::Unknown
+}
+
+fn is_numeric(ty: Type) -> bool {
+    matches!(ty, Type::Int | Type::Float | Type::Quantity(_) | Type::Unknown)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_source(source: &str) -> Vec<TypeError> {
+        check(&crate::parse_str(source).unwrap())
+    }
+
+    #[test]
+    fn suffixed_literals_are_range_checked() {
+        assert!(check_source("x = 255u8;").is_empty());
+        let errors = check_source("x = 256u8;");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].expected, "a value within u8");
+        assert!(check_source("x = 9223372036854775807i64;").is_empty());
+        assert_eq!(check_source("x = -1u32;").len(), 1);
+    }
+
+    #[test]
+    fn negative_literals_are_checked_with_their_sign() {
+        assert!(check_source("x = -128i8;").is_empty());
+        assert_eq!(check_source("x = -129i8;").len(), 1);
+        assert_eq!(check_source("x = 128i8;").len(), 1);
+        assert!(check_source("x = -(128i8);").is_empty());
+    }
+
+    #[test]
+    fn unsuffixed_literals_default_to_i32() {
+        let ast = crate::parse_str("x = 5;").unwrap();
+        assert!(matches!(&ast[..], [Stmt::Assignment(_, Expr::NumberLiteral(5, NumType::I32))]));
+        assert!(check_source("x = 2147483647;").is_empty());
+        assert!(check_source("x = -2147483648;").is_empty());
+        assert_eq!(check_source("x = 2147483648;").len(), 1);
+    }
+
+    #[test]
+    fn well_typed_program_passes() {
+        assert!(check_source("function startEngine() { speed = 100; if (speed > 60) { applyBrakes(); } }").is_empty());
+        assert!(check_source("let x = 1.5; x = x * 2; if (x > 1 && true) { y = \"a\"; } while (!false) {}").is_empty());
+    }
+
+    #[test]
+    fn non_bool_condition_is_an_error() {
+        let errors = check_source("if (100) {}");
+        assert_eq!(errors, vec![TypeError { node: "100".into(), expected: "Bool".into(), found: Type::Int, position: None }]);
+        let errors = check_source("if (5 + \"x\" > 1) {}");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].found, Type::Str);
+    }
+
+    #[test]
+    fn quantities_of_different_dimensions_do_not_add() {
+        assert_eq!(check_source("x = 100km/h + 5m;").len(), 1);
+        assert!(check_source("x = 100km/h + 5mph; y = 2 * 5m; if (x > 20km/h) {}").is_empty());
+    }
+
+    #[test]
+    fn ternary_condition_must_be_bool() {
+        assert_eq!(check_source("x = 5 ? 1 : 2;").len(), 1);
+        assert!(check_source("x = true ? 1 : 2;").is_empty());
+    }
+
+    #[test]
+    fn sensor_reads_are_floats() {
+        assert!(check_source("if (readSpeed() > 60) { f(); }").is_empty());
+        assert_eq!(check_source("if (readSpeed()) { f(); }").len(), 1);
+        assert!(check_source("if (other()) { f(); }").is_empty());
+    }
+
+    #[test]
+    fn ternary_branches_unify() {
+        assert!(check_source("c = true; x = c ? 1 : 2; y = x + 1;").is_empty());
+        assert_eq!(check_source("c = true; x = c ? 1 : 2; y = x && true;")[0].found, Type::Int);
+        assert_eq!(check_source("c = true; x = c ? 1 : 1.0; y = x && true;")[0].found, Type::Float);
+        let errors = check_source("c = true; x = c ? 1 : \"x\";");
+        assert_eq!(errors.len(), 1);
+        assert_eq!((errors[0].node.as_str(), errors[0].expected.as_str(), &errors[0].found), ("\"x\"", "Int", &Type::Str));
+    }
+}
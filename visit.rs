@@ -0,0 +1,162 @@
+use crate::parser::{Expr, Stmt, StringPart};
+
+// Every method defaults to recursing into the node's children, so an
+// implementation only overrides the nodes it cares about.
+pub trait Visitor {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr);
+    }
+
+    fn visit_unary(&mut self, _op: &str, operand: &Expr) {
+        self.visit_expr(operand);
+    }
+
+    fn visit_binary(&mut self, left: &Expr, _op: &str, right: &Expr) {
+        self.visit_expr(left);
+        self.visit_expr(right);
+    }
+
+    fn visit_call(&mut self, _name: &str, args: &[Expr]) {
+        for arg in args {
+            self.visit_expr(arg);
+        }
+    }
+}
+
+pub fn walk_stmts<V: Visitor + ?Sized>(visitor: &mut V, stmts: &[Stmt]) {
+    for stmt in stmts {
+        visitor.visit_stmt(stmt);
+    }
+}
+
+pub fn walk_stmt<V: Visitor + ?Sized>(visitor: &mut V, stmt: &Stmt) {
+    match stmt {
+        Stmt::VarDecl(_, value) | Stmt::Return(value) => {
+            if let Some(value) = value {
+                visitor.visit_expr(value);
+            }
+        }
+        Stmt::ConstDecl(_, value) | Stmt::Assignment(_, value) | Stmt::CompoundAssignment(_, _, value) | Stmt::Expression(value) => {
+            visitor.visit_expr(value);
+        }
+        Stmt::FunctionDeclaration(_, params, body, _) => {
+            for default in params.iter().filter_map(|(_, default)| default.as_ref()) {
+                visitor.visit_expr(default);
+            }
+            walk_stmts(visitor, body);
+        }
+        Stmt::EventHandler(_, body) | Stmt::Block(body) => walk_stmts(visitor, body),
+        Stmt::If(condition, body, else_body) => {
+            visitor.visit_expr(condition);
+            walk_stmts(visitor, body);
+            if let Some(else_body) = else_body {
+                walk_stmts(visitor, else_body);
+            }
+        }
+        Stmt::IfChain(arms, else_body) => {
+            for (condition, body) in arms {
+                visitor.visit_expr(condition);
+                walk_stmts(visitor, body);
+            }
+            if let Some(else_body) = else_body {
+                walk_stmts(visitor, else_body);
+            }
+        }
+        Stmt::Switch(subject, cases, default) => {
+            visitor.visit_expr(subject);
+            for (value, body) in cases {
+                visitor.visit_expr(value);
+                walk_stmts(visitor, body);
+            }
+            if let Some(default) = default {
+                walk_stmts(visitor, default);
+            }
+        }
+        Stmt::While(condition, body) => {
+            visitor.visit_expr(condition);
+            walk_stmts(visitor, body);
+        }
+        Stmt::ForRange(_, range, body) => {
+            visitor.visit_expr(range);
+            walk_stmts(visitor, body);
+        }
+        Stmt::ForClassic(init, condition, step, body) => {
+            visitor.visit_stmt(init);
+            visitor.visit_expr(condition);
+            visitor.visit_stmt(step);
+            walk_stmts(visitor, body);
+        }
+        Stmt::Break | Stmt::Continue | Stmt::Increment(..) | Stmt::Import(_) => {}
+    }
+}
+
+pub fn walk_expr<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expr) {
+    match expr {
+        Expr::NumberLiteral(..)
+        | Expr::FloatLiteral(_)
+        | Expr::BoolLiteral(_)
+        | Expr::StringLiteral(_)
+        | Expr::Quantity(..)
+        | Expr::Variable(..) => {}
+        Expr::Unary(op, operand) => visitor.visit_unary(op, operand),
+        Expr::Binary(left, op, right) => visitor.visit_binary(left, op, right),
+        Expr::Ternary(condition, then_branch, else_branch) => {
+            visitor.visit_expr(condition);
+            visitor.visit_expr(then_branch);
+            visitor.visit_expr(else_branch);
+        }
+        Expr::Assign(_, value, _) => visitor.visit_expr(value),
+        Expr::Call(name, args) => visitor.visit_call(name, args),
+        Expr::ArrayLiteral(elements) => {
+            for element in elements {
+                visitor.visit_expr(element);
+            }
+        }
+        Expr::Index(target, index) | Expr::Range(target, index) => {
+            visitor.visit_expr(target);
+            visitor.visit_expr(index);
+        }
+        Expr::Interpolation(parts) => {
+            for part in parts {
+                if let StringPart::Expr(expr) = part {
+                    visitor.visit_expr(expr);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct CallCounter {
+    pub count: usize,
+}
+
+impl Visitor for CallCounter {
+    fn visit_call(&mut self, _name: &str, args: &[Expr]) {
+        self.count += 1;
+        for arg in args {
+            self.visit_expr(arg);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count_calls(source: &str) -> usize {
+        let mut counter = CallCounter::default();
+        walk_stmts(&mut counter, &crate::parse_str(source).unwrap());
+        counter.count
+    }
+
+    #[test]
+    fn counts_every_call() {
+        assert_eq!(count_calls("applyBrakes(); honk();"), 2);
+        assert_eq!(count_calls("applyBrakes(); if (x) { honk(speed(1)); }"), 3);
+    }
+}
@@ -0,0 +1,276 @@
+use crate::parser::{Expr, NumType, Stmt, StringPart};
+
+// Emits a Graphviz digraph with one node per statement and expression,
+// labelled with the variant name and its most useful payload.
+pub fn to_dot(stmts: &[Stmt]) -> String {
+    let mut graph = DotGraph {
+        out: String::from("digraph ast {\n    node [shape=box];\n"),
+        next_id: 0,
+    };
+    let root = graph.node("Program");
+    graph.block(root, stmts);
+    graph.out.push_str("}\n");
+    graph.out
+}
+
+struct DotGraph {
+    out: String,
+    next_id: usize,
+}
+
+impl DotGraph {
+    fn node(&mut self, label: &str) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        let label = label.replace('\\', "\\\\").replace('"', "\\\"");
+        self.out.push_str(&format!("    n{} [label=\"{}\"];\n", id, label));
+        id
+    }
+
+    fn edge(&mut self, from: usize, to: usize) {
+        self.out.push_str(&format!("    n{} -> n{};\n", from, to));
+    }
+
+    fn block(&mut self, parent: usize, stmts: &[Stmt]) {
+        for stmt in stmts {
+            let child = self.stmt(stmt);
+            self.edge(parent, child);
+        }
+    }
+
+    fn child_expr(&mut self, parent: usize, expr: &Expr) {
+        let child = self.expr(expr);
+        self.edge(parent, child);
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> usize {
+        match stmt {
+            Stmt::VarDecl(name, value) => {
+                let id = self.node(&format!("VarDecl {}", name));
+                if let Some(value) = value {
+                    self.child_expr(id, value);
+                }
+                id
+            }
+            Stmt::ConstDecl(name, value) => {
+                let id = self.node(&format!("ConstDecl {}", name));
+                self.child_expr(id, value);
+                id
+            }
+            Stmt::Assignment(name, value) => {
+                let id = self.node(&format!("Assignment {}", name));
+                self.child_expr(id, value);
+                id
+            }
+            Stmt::Increment(name, op) => self.node(&format!("Increment {}{}", name, op)),
+            Stmt::CompoundAssignment(name, op, value) => {
+                let id = self.node(&format!("CompoundAssignment {} {}", name, op));
+                self.child_expr(id, value);
+                id
+            }
+            Stmt::FunctionDeclaration(name, params, body, pure) => {
+                let names: Vec<&str> = params.iter().map(|(param, _)| param.as_str()).collect();
+                let prefix = if *pure { "pure " } else { "" };
+                let id = self.node(&format!("FunctionDeclaration {}{}({})", prefix, name, names.join(", ")));
+                for default in params.iter().filter_map(|(_, default)| default.as_ref()) {
+                    self.child_expr(id, default);
+                }
+                self.block(id, body);
+                id
+            }
+            Stmt::If(condition, body, else_body) => {
+                let id = self.node("If");
+                self.child_expr(id, condition);
+                let then_id = self.node("Then");
+                self.edge(id, then_id);
+                self.block(then_id, body);
+                if let Some(else_body) = else_body {
+                    let else_id = self.node("Else");
+                    self.edge(id, else_id);
+                    self.block(else_id, else_body);
+                }
+                id
+            }
+            Stmt::IfChain(arms, else_body) => {
+                let id = self.node("IfChain");
+                for (condition, body) in arms {
+                    let arm_id = self.node("Arm");
+                    self.edge(id, arm_id);
+                    self.child_expr(arm_id, condition);
+                    self.block(arm_id, body);
+                }
+                if let Some(else_body) = else_body {
+                    let else_id = self.node("Else");
+                    self.edge(id, else_id);
+                    self.block(else_id, else_body);
+                }
+                id
+            }
+            Stmt::Switch(subject, cases, default) => {
+                let id = self.node("Switch");
+                self.child_expr(id, subject);
+                for (value, body) in cases {
+                    let case_id = self.node("Case");
+                    self.edge(id, case_id);
+                    self.child_expr(case_id, value);
+                    self.block(case_id, body);
+                }
+                if let Some(default) = default {
+                    let default_id = self.node("Default");
+                    self.edge(id, default_id);
+                    self.block(default_id, default);
+                }
+                id
+            }
+            Stmt::While(condition, body) => {
+                let id = self.node("While");
+                self.child_expr(id, condition);
+                self.block(id, body);
+                id
+            }
+            Stmt::ForRange(name, range, body) => {
+                let id = self.node(&format!("ForRange {}", name));
+                self.child_expr(id, range);
+                self.block(id, body);
+                id
+            }
+            Stmt::ForClassic(init, condition, step, body) => {
+                let id = self.node("ForClassic");
+                let init = self.stmt(init);
+                self.edge(id, init);
+                self.child_expr(id, condition);
+                let step = self.stmt(step);
+                self.edge(id, step);
+                self.block(id, body);
+                id
+            }
+            Stmt::Block(body) => {
+                let id = self.node("Block");
+                self.block(id, body);
+                id
+            }
+            Stmt::Break => self.node("Break"),
+            Stmt::Continue => self.node("Continue"),
+            Stmt::Import(path) => self.node(&format!("Import {:?}", path)),
+            Stmt::Return(value) => {
+                let id = self.node("Return");
+                if let Some(value) = value {
+                    self.child_expr(id, value);
+                }
+                id
+            }
+            Stmt::EventHandler(event, body) => {
+                let id = self.node(&format!("EventHandler {}", event));
+                self.block(id, body);
+                id
+            }
+            Stmt::Expression(expr) => {
+                let id = self.node("Expression");
+                self.child_expr(id, expr);
+                id
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) -> usize {
+        match expr {
+            Expr::NumberLiteral(value, NumType::I32) => self.node(&format!("NumberLiteral {}", value)),
+            Expr::NumberLiteral(value, ty) => self.node(&format!("NumberLiteral {}{}", value, ty.suffix())),
+            Expr::FloatLiteral(value) => self.node(&format!("FloatLiteral {:?}", value)),
+            Expr::BoolLiteral(value) => self.node(&format!("BoolLiteral {}", value)),
+            Expr::StringLiteral(value) => self.node(&format!("StringLiteral {:?}", value)),
+            Expr::Quantity(value, unit) => self.node(&format!("Quantity {}{}", value, unit.suffix())),
+            Expr::Variable(name, _) => self.node(&format!("Variable {}", name)),
+            Expr::Unary(op, operand) => {
+                let id = self.node(&format!("Unary {}", op));
+                self.child_expr(id, operand);
+                id
+            }
+            Expr::Binary(left, op, right) => {
+                let id = self.node(&format!("Binary {}", op));
+                self.child_expr(id, left);
+                self.child_expr(id, right);
+                id
+            }
+            Expr::Ternary(condition, then_branch, else_branch) => {
+                let id = self.node("Ternary");
+                self.child_expr(id, condition);
+                self.child_expr(id, then_branch);
+                self.child_expr(id, else_branch);
+                id
+            }
+            Expr::Assign(name, value, _) => {
+                let id = self.node(&format!("Assign {}", name));
+                self.child_expr(id, value);
+                id
+            }
+            Expr::Call(name, args) => {
+                let id = self.node(&format!("Call {}", name));
+                for arg in args {
+                    self.child_expr(id, arg);
+                }
+                id
+            }
+            Expr::ArrayLiteral(elements) => {
+                let id = self.node("ArrayLiteral");
+                for element in elements {
+                    self.child_expr(id, element);
+                }
+                id
+            }
+            Expr::Index(target, index) => {
+                let id = self.node("Index");
+                self.child_expr(id, target);
+                self.child_expr(id, index);
+                id
+            }
+            Expr::Range(start, end) => {
+                let id = self.node("Range");
+                self.child_expr(id, start);
+                self.child_expr(id, end);
+                id
+            }
+            Expr::Interpolation(parts) => {
+                let id = self.node("Interpolation");
+                for part in parts {
+                    match part {
+                        StringPart::Literal(text) => {
+                            let child = self.node(&format!("Literal {:?}", text));
+                            self.edge(id, child);
+                        }
+                        StringPart::Expr(expr) => self.child_expr(id, expr),
+                    }
+                }
+                id
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn readme_renders_labelled_nodes() {
+        let source = "
+        function startEngine() {
+            speed = 100;
+            if (speed > 60) {
+                applyBrakes();
+            }
+        }
+    ";
+        let dot = to_dot(&crate::parse_str(source).unwrap());
+        assert!(dot.starts_with("digraph ast {") && dot.ends_with("}\n"), "{}", dot);
+        for label in ["FunctionDeclaration startEngine()", "Binary >", "If", "Call applyBrakes", "NumberLiteral 60"] {
+            assert!(dot.contains(&format!("label=\"{}\"", label)), "{}", dot);
+        }
+    }
+
+    #[test]
+    fn labels_are_escaped() {
+        let dot = to_dot(&crate::parse_str("x = \"a\\\\b\";").unwrap());
+        assert!(dot.contains("StringLiteral \\\""), "{}", dot);
+    }
+}
@@ -0,0 +1,400 @@
+use std::collections::HashMap;
+
+use crate::compiler::{Function, Op, Program};
+use crate::interpreter::{eval_binary, eval_unary, NativeFunction, RuntimeError, Value};
+
+// What a variable slot is bound to: a value, or a function by its index in
+// `Program::functions`.
+#[derive(Clone)]
+enum Slot {
+    Value(Value),
+    Function(usize),
+}
+
+// The locals of one active call, or of the top-level code.
+struct Frame {
+    locals: Vec<Option<Slot>>,
+    // Where the caller resumes; unused for a frame entered from outside the
+    // VM.
+    return_to: usize,
+}
+
+impl Frame {
+    fn new(size: usize, return_to: usize) -> Self {
+        Frame {
+            locals: vec![None; size],
+            return_to,
+        }
+    }
+}
+
+pub struct Vm {
+    program: Program,
+    globals: Vec<Option<Slot>>,
+    stack: Vec<Value>,
+    frames: Vec<Frame>,
+    natives: HashMap<String, NativeFunction>,
+}
+
+impl Vm {
+    pub fn new(program: Program) -> Self {
+        let globals = vec![None; program.globals.len()];
+        Vm {
+            program,
+            globals,
+            stack: Vec::new(),
+            frames: Vec::new(),
+            natives: HashMap::new(),
+        }
+    }
+
+    pub fn register_native(&mut self, name: &str, function: impl Fn(&[Value]) -> Result<Value, RuntimeError> + 'static) {
+        self.natives.insert(name.to_string(), Box::new(function));
+    }
+
+    // Reads a global; locals are gone once their call returns, and
+    // functions are not values here.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        match self.global(name)? {
+            Slot::Value(value) => Some(value),
+            Slot::Function(_) => None,
+        }
+    }
+
+    fn global(&self, name: &str) -> Option<&Slot> {
+        let slot = self.program.globals.iter().position(|global| global == name)?;
+        self.globals[slot].as_ref()
+    }
+
+    pub fn run(&mut self) -> Result<(), RuntimeError> {
+        let depth = self.frames.len();
+        self.frames.push(Frame::new(self.program.locals.len(), 0));
+        let result = self.execute(0);
+        self.frames.truncate(depth);
+        result.map(|_| ())
+    }
+
+    pub fn call(&mut self, name: &str, args: Vec<Value>) -> Result<Value, RuntimeError> {
+        let argc = args.len();
+        self.stack.extend(args);
+        let depth = self.frames.len();
+        let callee = self.global(name).cloned();
+        let result = match self.enter(callee, name, argc, 0) {
+            Ok(Some(entry)) => self.execute(entry),
+            Ok(None) => Ok(self.pop()),
+            Err(err) => Err(err),
+        };
+        self.frames.truncate(depth);
+        result
+    }
+
+    pub fn dispatch(&mut self, event: &str) -> Result<(), RuntimeError> {
+        if let Some(handler) = self.program.handlers.get(event) {
+            let entry = handler.entry;
+            let depth = self.frames.len();
+            self.frames.push(Frame::new(handler.locals.len(), 0));
+            let result = self.execute(entry);
+            self.frames.truncate(depth);
+            result?;
+        }
+        Ok(())
+    }
+
+    fn frame(&mut self) -> &mut Frame {
+        self.frames.last_mut().expect("code runs in a frame")
+    }
+
+    fn local(&self, slot: usize) -> Option<Slot> {
+        self.frames.last().expect("code runs in a frame").locals[slot].clone()
+    }
+
+    // Runs from `pc` in the frame on top until that frame's `Return`,
+    // yielding its value. The frame itself is left for the caller to drop.
+    fn execute(&mut self, mut pc: usize) -> Result<Value, RuntimeError> {
+        let base = self.frames.len();
+        loop {
+            let op = self.program.code[pc].clone();
+            pc += 1;
+            match op {
+                Op::PushInt(value) => self.stack.push(Value::Int(value)),
+                Op::PushFloat(value) => self.stack.push(Value::Float(value)),
+                Op::PushBool(value) => self.stack.push(Value::Bool(value)),
+                Op::PushStr(value) => self.stack.push(Value::Str(value)),
+                Op::PushUnit => self.stack.push(Value::Unit),
+                Op::LoadGlobal(slot) => {
+                    let value = self.load(self.globals[slot].clone(), &self.program.globals[slot])?;
+                    self.stack.push(value);
+                }
+                Op::StoreGlobal(slot) => self.globals[slot] = Some(Slot::Value(self.pop())),
+                Op::LoadLocal(slot) => {
+                    let value = self.load(self.local(slot), &self.local_name(pc - 1, slot))?;
+                    self.stack.push(value);
+                }
+                Op::StoreLocal(slot) => {
+                    let value = self.pop();
+                    self.frame().locals[slot] = Some(Slot::Value(value));
+                }
+                Op::DefineGlobal(slot, function) => self.globals[slot] = Some(Slot::Function(function)),
+                Op::DefineLocal(slot, function) => self.frame().locals[slot] = Some(Slot::Function(function)),
+                Op::Pop => {
+                    self.pop();
+                }
+                Op::Neg | Op::Not => {
+                    let operand = self.pop();
+                    let op = if op == Op::Neg { "-" } else { "!" };
+                    self.stack.push(eval_unary(op, operand)?);
+                }
+                Op::Jump(target) => pc = target,
+                Op::JumpIfFalse(target) => match self.pop() {
+                    Value::Bool(true) => {}
+                    Value::Bool(false) => pc = target,
+                    other => {
+                        return Err(RuntimeError::TypeMismatch(format!("condition must be a bool, found {:?}", other)))
+                    }
+                },
+                Op::CallGlobal(slot, argc) => {
+                    let name = self.program.globals[slot].clone();
+                    if let Some(entry) = self.enter(self.globals[slot].clone(), &name, argc, pc)? {
+                        pc = entry;
+                    }
+                }
+                Op::CallLocal(slot, argc) => {
+                    let name = self.local_name(pc - 1, slot);
+                    if let Some(entry) = self.enter(self.local(slot), &name, argc, pc)? {
+                        pc = entry;
+                    }
+                }
+                Op::CallFunction(function, argc) => {
+                    let name = self.program.functions[function].name.clone();
+                    if let Some(entry) = self.enter(Some(Slot::Function(function)), &name, argc, pc)? {
+                        pc = entry;
+                    }
+                }
+                Op::Concat(count) => {
+                    let parts = self.stack.split_off(self.stack.len() - count);
+                    self.stack.push(Value::Str(parts.iter().map(Value::to_string).collect()));
+                }
+                Op::Return => {
+                    let value = self.pop();
+                    if self.frames.len() == base {
+                        return Ok(value);
+                    }
+                    pc = self.frames.pop().expect("frame above base").return_to;
+                    self.stack.push(value);
+                }
+                _ => {
+                    let symbol = op.binary_symbol().expect("remaining ops are binary");
+                    let right = self.pop();
+                    let left = self.pop();
+                    self.stack.push(eval_binary(left, symbol, right)?);
+                }
+            }
+        }
+    }
+
+    fn load(&self, slot: Option<Slot>, name: &str) -> Result<Value, RuntimeError> {
+        match slot {
+            Some(Slot::Value(value)) => Ok(value),
+            Some(Slot::Function(_)) => Err(RuntimeError::Unsupported(format!("function {} used as a value", name))),
+            None => Err(RuntimeError::UndefinedVariable(name.to_string())),
+        }
+    }
+
+    // Binds the `argc` arguments on the stack for a call to `name`, bound to
+    // `callee`. A compiled function gets a new frame, returning to
+    // `return_to`, and returns its entry point; a native, called when the
+    // name is not bound, is run immediately and leaves its result on the
+    // stack.
+    fn enter(&mut self, callee: Option<Slot>, name: &str, argc: usize, return_to: usize) -> Result<Option<usize>, RuntimeError> {
+        let args = self.stack.split_off(self.stack.len() - argc);
+        if let Some(Slot::Value(value)) = callee {
+            return Err(RuntimeError::TypeMismatch(format!("{} is not a function: {:?}", name, value)));
+        }
+        if let Some(Slot::Function(function)) = callee {
+            let function = self.program.functions[function].clone();
+            let required = function.defaults.iter().filter(|default| default.is_none()).count();
+            if argc < required || argc > function.params.len() {
+                let expected = if argc < required { required } else { function.params.len() };
+                return Err(RuntimeError::ArityMismatch(name.to_string(), expected, argc));
+            }
+            self.frames.push(Frame::new(function.locals.len(), return_to));
+            let mut args = args.into_iter();
+            for (&slot, default) in function.params.iter().zip(&function.defaults) {
+                let value = match (args.next(), default) {
+                    (Some(arg), _) => arg,
+                    (None, Some(default)) => self.execute(*default)?,
+                    (None, None) => unreachable!("arity checked above"),
+                };
+                self.frame().locals[slot] = Some(Slot::Value(value));
+            }
+            return Ok(Some(function.entry));
+        }
+        match self.natives.get(name) {
+            Some(native) => {
+                let value = native(&args)?;
+                self.stack.push(value);
+                Ok(None)
+            }
+            None => Err(RuntimeError::UndefinedFunction(name.to_string())),
+        }
+    }
+
+    // The name of a local slot of the code at `pc`, for an error. Each body
+    // is laid out after its entry point, with its defaults, ahead of the
+    // next body's.
+    fn local_name(&self, pc: usize, slot: usize) -> String {
+        let functions = self.program.functions.iter().chain(self.program.handlers.values());
+        let body = functions.filter(|function| function.entry <= pc).max_by_key(|function| function.entry);
+        let locals = body.map_or(&self.program.locals, |function: &Function| &function.locals);
+        locals[slot].clone()
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("compiled code keeps the stack balanced")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::compiler::{compile, CompileError};
+    use crate::interpreter::Interpreter;
+
+    const README: &str = "
+        function startEngine() {
+            speed = 100;
+            if (speed > 60) {
+                applyBrakes();
+            }
+        }
+    ";
+
+    // Runs `source` on both engines, checking they agree on whether it
+    // fails and on the value of each of `names` afterwards.
+    fn run_both(source: &str, names: &[&str]) -> (Interpreter, Vm) {
+        let ast = crate::parse_str(source).unwrap();
+        let mut interpreter = Interpreter::new();
+        let interpreted = interpreter.run(&ast);
+        let mut vm = Vm::new(compile(&ast).unwrap());
+        let compiled = vm.run();
+        assert_eq!(interpreted, compiled, "{}", source);
+        for name in names {
+            assert_eq!(interpreter.get(name), vm.get(name), "{} in {}", name, source);
+        }
+        (interpreter, vm)
+    }
+
+    #[test]
+    fn readme_program_matches_interpreter() {
+        let (mut interpreter, mut vm) = run_both(README, &[]);
+        let brakes = Rc::new(Cell::new(0));
+        let counter = brakes.clone();
+        interpreter.register_native("applyBrakes", move |_| {
+            counter.set(counter.get() + 1);
+            Ok(Value::Unit)
+        });
+        let counter = brakes.clone();
+        vm.register_native("applyBrakes", move |_| {
+            counter.set(counter.get() + 1);
+            Ok(Value::Unit)
+        });
+        assert_eq!(interpreter.call("startEngine", Vec::new()), vm.call("startEngine", Vec::new()));
+        assert_eq!(brakes.get(), 2);
+        assert_eq!(interpreter.get("speed"), vm.get("speed"));
+        assert_eq!(vm.get("speed"), Some(&Value::Int(100)));
+    }
+
+    #[test]
+    fn loop_program_matches_interpreter() {
+        let source = "
+            i = 0;
+            total = 0;
+            while (i < 10) {
+                i += 1;
+                if (i % 3 == 0) { continue; }
+                if (i > 8) { break; }
+                total = total + i * 2;
+            }
+            x = i > 5 ? 1 : 2;
+        ";
+        let (_, vm) = run_both(source, &["i", "total", "x"]);
+        assert_eq!(vm.get("total"), Some(&Value::Int(54)));
+    }
+
+    #[test]
+    fn nested_functions_are_scoped_to_their_body() {
+        let source = "
+            function a() { function h() { return 1; } return h() + 10; }
+            function b() { function h() { return 2; } return h() + 10; }
+            r = a();
+        ";
+        let (_, vm) = run_both(source, &["r"]);
+        assert_eq!(vm.get("r"), Some(&Value::Int(11)));
+        let source = "function f() { function h() { return 1; } return h(); } f(); r = h();";
+        let (_, mut vm) = run_both(source, &["r"]);
+        assert_eq!(vm.run(), Err(RuntimeError::UndefinedFunction("h".to_string())));
+    }
+
+    #[test]
+    fn functions_are_only_callable_once_declared() {
+        let (_, mut vm) = run_both("r = f(); function f() { return 3; }", &["r"]);
+        assert_eq!(vm.run(), Err(RuntimeError::UndefinedFunction("f".to_string())));
+        run_both("function g() { return f(); } function f() { return 3; } r = g();", &["r"]);
+    }
+
+    #[test]
+    fn nested_functions_call_each_other_and_recurse() {
+        let source = "
+            function outer(n) {
+                function down(k) { if (k <= 0) { return 0; } return down(k - 1) + 1; }
+                function twice(k) { return down(k) * 2; }
+                return twice(n);
+            }
+            r = outer(4);
+        ";
+        let (_, vm) = run_both(source, &["r"]);
+        assert_eq!(vm.get("r"), Some(&Value::Int(8)));
+    }
+
+    #[test]
+    fn ints_are_64_bits() {
+        let (_, vm) = run_both("x = 2000000000 + 2000000000; y = 2 + 1.5 == 3.5; z = 100i64 < 100.5;", &["x", "y", "z"]);
+        assert_eq!(vm.get("x"), Some(&Value::Int(4_000_000_000)));
+        run_both("x = 9223372036854775807i64 + 1;", &[]);
+    }
+
+    #[test]
+    fn calls_get_their_own_frame() {
+        let (_, vm) = run_both("function fact(n) { if (n <= 1) { return 1; } return fact(n - 1) * n; } r = fact(5);", &["r", "n"]);
+        assert_eq!(vm.get("r"), Some(&Value::Int(120)));
+        let (_, vm) = run_both("function f(x) { return x; } x = 5; y = f(1);", &["x", "y"]);
+        assert_eq!(vm.get("x"), Some(&Value::Int(5)));
+        run_both("function h() { n = 7; } h();", &["n"]);
+        run_both("function h() { let n = 7; } n = 1; h();", &["n"]);
+        run_both("function g(p, q = p + 1) { let z = q; { let z = 9; } return z; } r = g(1); w = g(1, 5);", &["r", "w", "z", "q"]);
+        run_both("function f(n) { switch (n) { case 1 { return f(2) + 10; } default { return n; } } } r = f(1);", &["r"]);
+    }
+
+    #[test]
+    fn block_and_loop_locals_do_not_leak() {
+        run_both("{ let a = 1; b = a; }", &["a", "b"]);
+        run_both("{ let a = 1; } c = a;", &["a", "c"]);
+        run_both("a = 1; { let a = 2; a = 3; b = a; } c = a;", &["a", "b", "c"]);
+        run_both("for (let i = 0; i < 3; i++) { s = i; }", &["i", "s"]);
+        run_both("for (let i = 0; i < 3; i++) { } t = i;", &["i", "t"]);
+        run_both("for (k in 0..3) { s = k; }", &["k", "s"]);
+        let (_, mut vm) = run_both("x = 0; on tick { let y = 2; x = x + y; }", &["x", "y"]);
+        vm.dispatch("tick").unwrap();
+        vm.dispatch("tick").unwrap();
+        assert_eq!(vm.get("x"), Some(&Value::Int(4)));
+    }
+
+    #[test]
+    fn closing_over_a_local_is_unsupported() {
+        let ast = crate::parse_str("function f(n) { function g() { return n; } return g(); }").unwrap();
+        assert!(matches!(compile(&ast), Err(CompileError::Unsupported(_))));
+    }
+}